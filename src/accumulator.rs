@@ -0,0 +1,134 @@
+//! Facts summed up ingredient by ingredient, as editors adding ingredients
+//! one at a time need them.
+
+use crate::{
+    error::RecipeError,
+    recipe::{check, NutritionFacts, Product},
+};
+
+/// Nutrients and raw weight of the ingredients added so far.
+///
+/// # Examples
+///
+/// ```
+/// use nutritions::{Nutrition, NutritionAccumulator, Product};
+///
+/// let oil = Product::new("Oil", [(Nutrition::Energy, 900.0)]);
+/// let mut accumulator = NutritionAccumulator::new();
+/// accumulator.add_product(&oil, 20.0)?;
+/// accumulator.add_product(&oil, 30.0)?;
+///
+/// assert_eq!(accumulator.raw_weight(), 50.0);
+/// assert_eq!(accumulator.finalize(50.0).get(Nutrition::Energy), Some(900.0));
+/// # Ok::<(), nutritions::RecipeError>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NutritionAccumulator {
+    totals_for_dish: NutritionFacts,
+    total_ingredients_weight: f64,
+}
+
+impl NutritionAccumulator {
+    /// Accumulator without any ingredient.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `amount_g` grams of `product`. Fails with `UnresolvedProduct`
+    /// for products made by recipes or blended from other products, which
+    /// take their recipe to compute.
+    pub fn add_product(&mut self, product: &Product, amount_g: f64) -> Result<(), RecipeError> {
+        check(|| "amount_g".into(), amount_g, |grams| grams >= 0.0)?;
+        let facts = product.facts_per_100g()?;
+        self.add(facts * (amount_g / 100.0), amount_g);
+        Ok(())
+    }
+
+    /// Adds the `nutrients` of an ingredient weighing `grams`.
+    pub(crate) fn add(&mut self, nutrients: NutritionFacts, grams: f64) {
+        self.totals_for_dish = std::mem::take(&mut self.totals_for_dish) + nutrients;
+        self.total_ingredients_weight += grams;
+    }
+
+    /// Nutrients of all the ingredients added.
+    pub fn total_nutrition(&self) -> &NutritionFacts {
+        &self.totals_for_dish
+    }
+
+    /// Grams the ingredients added weigh raw.
+    pub fn raw_weight(&self) -> f64 {
+        self.total_ingredients_weight
+    }
+
+    /// Facts per 100g of a dish weighing `dish_weight` grams made of the
+    /// ingredients added, which is their [`NutritionAccumulator::raw_weight`]
+    /// unless cooking changes it. The weight is to be positive.
+    pub fn finalize(self, dish_weight: f64) -> NutritionFacts {
+        self.totals_for_dish * (100.0 / dish_weight)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NutritionAccumulator;
+    use crate::{error::RecipeError, Nutrition, Product, Recipe};
+
+    #[tokio::test]
+    async fn accumulate_like_recipe() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Milk
+    facts:
+      Energy: 60
+      Proteins: 3
+  - name: Granola
+    per:
+      amount: 30
+    facts:
+      Energy: 135
+      Proteins: 3
+dish:
+  weight: 200
+  ingredients:
+    - product: Milk
+      amount: 200
+    - product: Granola
+      amount: 60
+"#,
+        )
+        .unwrap();
+        let milk = Product::new(
+            "Milk",
+            [(Nutrition::Energy, 60.0), (Nutrition::Proteins, 3.0)],
+        );
+        let granola = Product::new(
+            "Granola",
+            [(Nutrition::Energy, 135.0), (Nutrition::Proteins, 3.0)],
+        )
+        .per(30.0, "g");
+
+        let mut accumulator = NutritionAccumulator::new();
+        accumulator.add_product(&milk, 200.0).unwrap();
+        let partial = accumulator.total_nutrition().clone();
+        accumulator.add_product(&granola, 60.0).unwrap();
+
+        assert_eq!(partial.get(Nutrition::Energy), Some(120.0));
+        assert_eq!(accumulator.raw_weight(), 260.0);
+        assert_eq!(
+            accumulator.finalize(200.0),
+            recipe.get_nutrition_facts().await.unwrap()
+        );
+    }
+
+    #[test]
+    fn fail_unresolved_product() {
+        let soup = Product::from_recipe("Soup", "soup.yaml");
+
+        match NutritionAccumulator::new().add_product(&soup, 100.0) {
+            Err(RecipeError::UnresolvedProduct { product }) => assert_eq!(product, "Soup"),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(()) => panic!("products made by recipes can't be added on their own"),
+        }
+    }
+}
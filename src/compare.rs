@@ -0,0 +1,84 @@
+//! Side-by-side comparison of the facts of two recipes.
+
+use crate::recipe::{Nutrition, NutritionFacts};
+
+/// Amounts of a nutrient in two compared facts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NutrientDelta {
+    pub nutrient: Nutrition,
+    pub left: f64,
+    pub right: f64,
+}
+
+impl NutrientDelta {
+    /// How much more of the nutrient the right side has.
+    pub fn delta(&self) -> f64 {
+        self.right - self.left
+    }
+
+    /// Delta in percent of the left amount, `None` when the left side has
+    /// none of the nutrient.
+    pub fn percentage(&self) -> Option<f64> {
+        if self.left == 0.0 {
+            None
+        } else {
+            Some(self.delta() / self.left * 100.0)
+        }
+    }
+}
+
+impl NutritionFacts {
+    /// Amounts of every nutrient present on either side in display order,
+    /// zero for the side that lacks it.
+    pub fn compare(&self, other: &NutritionFacts) -> Vec<NutrientDelta> {
+        let union = self.clone() + other.clone() * 0.0;
+        union
+            .nutrients()
+            .map(|nutrient| NutrientDelta {
+                nutrient: nutrient.clone(),
+                left: self.0.get(nutrient).copied().unwrap_or(0.0),
+                right: other.0.get(nutrient).copied().unwrap_or(0.0),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NutrientDelta;
+    use crate::{Nutrition, NutritionFacts};
+
+    #[test]
+    fn compare() {
+        let left = NutritionFacts::from_iter([(Nutrition::Energy, 200.0), (Nutrition::Fats, 10.0)]);
+        let right =
+            NutritionFacts::from_iter([(Nutrition::Energy, 250.0), (Nutrition::Proteins, 5.0)]);
+
+        let deltas = left.compare(&right);
+
+        assert_eq!(
+            deltas,
+            [
+                NutrientDelta {
+                    nutrient: Nutrition::Energy,
+                    left: 200.0,
+                    right: 250.0
+                },
+                NutrientDelta {
+                    nutrient: Nutrition::Proteins,
+                    left: 0.0,
+                    right: 5.0
+                },
+                NutrientDelta {
+                    nutrient: Nutrition::Fats,
+                    left: 10.0,
+                    right: 0.0
+                },
+            ]
+        );
+        assert_eq!(deltas[0].delta(), 50.0);
+        assert_eq!(deltas[0].percentage(), Some(25.0));
+        assert_eq!(deltas[1].percentage(), None);
+        assert_eq!(deltas[2].percentage(), Some(-100.0));
+    }
+}
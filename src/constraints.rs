@@ -0,0 +1,180 @@
+//! Limits on nutrients that recipes are validated against.
+
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    error::RecipeError,
+    recipe::{check, Nutrition, NutritionFacts},
+};
+
+/// Lowest and highest allowed amount of a nutrient, either may be omitted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limits {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Limits of nutrients, in the units of [`Nutrition::unit`], for the basis
+/// the checked facts are given for.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Constraints(BTreeMap<Nutrition, Limits>);
+
+impl Constraints {
+    /// Reads limits from a YAML mapping of nutrient names to `min` and `max`.
+    pub async fn read_from_file(path: &Path) -> Result<Self, RecipeError> {
+        let yaml = tokio::fs::read_to_string(path).await?;
+        Self::from_yaml_str(&yaml).map_err(|err| err.in_file(path))
+    }
+
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, RecipeError> {
+        let constraints: Constraints = serde_yaml::from_str(yaml)?;
+        for (nutrient, limits) in &constraints.0 {
+            if let Some(min) = limits.min {
+                check(
+                    || format!("constraints.{}.min", nutrient),
+                    min,
+                    |v| v >= 0.0,
+                )?;
+            }
+            if let Some(max) = limits.max {
+                check(
+                    || format!("constraints.{}.max", nutrient),
+                    max,
+                    |v| v >= limits.min.unwrap_or(0.0),
+                )?;
+            }
+        }
+        Ok(constraints)
+    }
+
+    pub fn get(&self, nutrient: Nutrition) -> Option<Limits> {
+        self.0.get(&nutrient).copied()
+    }
+
+    pub fn insert(&mut self, nutrient: Nutrition, limits: Limits) {
+        self.0.insert(nutrient, limits);
+    }
+}
+
+/// Nutrient whose amount falls outside of its limits.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    Below {
+        nutrient: Nutrition,
+        amount: f64,
+        min: f64,
+    },
+    Above {
+        nutrient: Nutrition,
+        amount: f64,
+        max: f64,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (nutrient, amount, relation, limit) = match self {
+            Violation::Below {
+                nutrient,
+                amount,
+                min,
+            } => (nutrient, amount, "below the minimum", min),
+            Violation::Above {
+                nutrient,
+                amount,
+                max,
+            } => (nutrient, amount, "above the maximum", max),
+        };
+        let unit = match nutrient.unit() {
+            "" => String::new(),
+            unit => format!(" {}", unit),
+        };
+        write!(
+            f,
+            "{} is {:.2}{}, {} of {}{}",
+            nutrient, amount, unit, relation, limit, unit
+        )
+    }
+}
+
+impl NutritionFacts {
+    /// Nutrients outside of their limits in display order, missing nutrients
+    /// counting as none.
+    pub fn check(&self, constraints: &Constraints) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for (nutrient, limits) in &constraints.0 {
+            let amount = self.0.get(nutrient).copied().unwrap_or(0.0);
+            if let Some(min) = limits.min.filter(|min| amount < *min) {
+                violations.push(Violation::Below {
+                    nutrient: nutrient.clone(),
+                    amount,
+                    min,
+                });
+            }
+            if let Some(max) = limits.max.filter(|max| amount > *max) {
+                violations.push(Violation::Above {
+                    nutrient: nutrient.clone(),
+                    amount,
+                    max,
+                });
+            }
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Constraints, Violation};
+    use crate::{error::RecipeError, Nutrition, NutritionFacts};
+
+    #[test]
+    fn check_constraints() {
+        let constraints = Constraints::from_yaml_str(
+            "Sodium:\n  max: 500\nProteins:\n  min: 10\n  max: 30\nFiber:\n  min: 3\n",
+        )
+        .unwrap();
+        let facts =
+            NutritionFacts::from_iter([(Nutrition::Proteins, 12.0), (Nutrition::Sodium, 620.0)]);
+
+        let violations = facts.check(&constraints);
+
+        assert_eq!(
+            violations,
+            [
+                Violation::Below {
+                    nutrient: Nutrition::Fiber,
+                    amount: 0.0,
+                    min: 3.0
+                },
+                Violation::Above {
+                    nutrient: Nutrition::Sodium,
+                    amount: 620.0,
+                    max: 500.0
+                },
+            ]
+        );
+        assert_eq!(
+            violations[1].to_string(),
+            "Sodium is 620.00 mg, above the maximum of 500 mg"
+        );
+    }
+
+    #[test]
+    fn fail_invalid_constraints() {
+        for (yaml, expected) in [
+            ("Sodium:\n  max: -1\n", "constraints.Sodium.max"),
+            ("Fats:\n  min: 20\n  max: 10\n", "constraints.Fats.max"),
+            ("Fats:\n  min: .nan\n", "constraints.Fats.min"),
+        ] {
+            match Constraints::from_yaml_str(yaml).unwrap_err() {
+                RecipeError::InvalidValue { field, .. } => assert_eq!(field, expected),
+                err => panic!("unexpected error: {}", err),
+            }
+        }
+        assert!(Constraints::from_yaml_str("Fats:\n  maximum: 10\n").is_err());
+    }
+}
@@ -0,0 +1,101 @@
+//! Reference daily intakes that nutrition facts are compared against.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    error::RecipeError,
+    recipe::{check, Nutrition, NutritionFacts},
+};
+
+/// Reference daily intake of nutrients, in the units of [`Nutrition::unit`].
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct DailyValues(BTreeMap<Nutrition, f64>);
+
+impl Default for DailyValues {
+    /// Intakes of a 2000 kcal diet.
+    fn default() -> Self {
+        Self(
+            [
+                (Nutrition::Energy, 2000.0),
+                (Nutrition::Proteins, 50.0),
+                (Nutrition::Fats, 70.0),
+                (Nutrition::Carbohydrates, 260.0),
+                (Nutrition::Sodium, 2300.0),
+            ]
+            .into_iter()
+            .collect(),
+        )
+    }
+}
+
+impl DailyValues {
+    /// Reads intakes from a YAML mapping of nutrient names to amounts.
+    pub async fn read_from_file(path: &Path) -> Result<Self, RecipeError> {
+        let yaml = tokio::fs::read_to_string(path).await?;
+        Self::from_yaml_str(&yaml).map_err(|err| err.in_file(path))
+    }
+
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, RecipeError> {
+        let daily_values: DailyValues = serde_yaml::from_str(yaml)?;
+        for (nutrient, value) in &daily_values.0 {
+            check(|| format!("daily_values.{}", nutrient), *value, |v| v > 0.0)?;
+        }
+        Ok(daily_values)
+    }
+
+    pub fn get(&self, nutrient: Nutrition) -> Option<f64> {
+        self.0.get(&nutrient).copied()
+    }
+}
+
+impl NutritionFacts {
+    /// Share of the daily intake, in percent, of every nutrient that has a
+    /// reference value.
+    pub fn daily_value_percentages(&self, dv: &DailyValues) -> BTreeMap<Nutrition, f64> {
+        self.0
+            .iter()
+            .filter_map(|(nutrient, amount)| {
+                let daily = dv.0.get(nutrient)?;
+                Some((nutrient.clone(), amount / daily * 100.0))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DailyValues;
+    use crate::{error::RecipeError, Nutrition, NutritionFacts};
+
+    #[test]
+    fn daily_value_percentages() {
+        let facts = NutritionFacts::from_iter([
+            (Nutrition::Energy, 500.0),
+            (Nutrition::Sodium, 230.0),
+            (Nutrition::Fiber, 3.0),
+        ]);
+
+        let percentages = facts.daily_value_percentages(&DailyValues::default());
+
+        assert_eq!(
+            percentages,
+            [(Nutrition::Energy, 25.0), (Nutrition::Sodium, 10.0)]
+                .into_iter()
+                .collect()
+        );
+    }
+
+    #[test]
+    fn custom_daily_values() {
+        let dv = DailyValues::from_yaml_str("Energy: 2500\nFiber: 30\n").unwrap();
+
+        assert_eq!(dv.get(Nutrition::Fiber), Some(30.0));
+        assert_eq!(dv.get(Nutrition::Sodium), None);
+        match DailyValues::from_yaml_str("Energy: 0\n").unwrap_err() {
+            RecipeError::InvalidValue { field, .. } => assert_eq!(field, "daily_values.Energy"),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Relations between energy and macronutrients based on Atwater factors.
+
+use std::fmt;
+
+use crate::recipe::{NutrientGroup, Nutrition, NutritionFacts};
+
+/// Energy in kcal provided by a gram of proteins, fats and carbohydrates.
+const ATWATER_FACTORS: [(Nutrition, f64); 3] = [
+    (Nutrition::Proteins, 4.0),
+    (Nutrition::Fats, 9.0),
+    (Nutrition::Carbohydrates, 4.0),
+];
+
+/// Relative difference between declared and estimated energy above which
+/// the declared value is likely a data-entry mistake.
+pub const ENERGY_DISCREPANCY_THRESHOLD: f64 = 0.15;
+
+/// Shares of energy, in percent, that come from each macronutrient.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MacroRatios {
+    pub proteins: f64,
+    pub fats: f64,
+    pub carbohydrates: f64,
+}
+
+impl NutritionFacts {
+    /// Energy in kcal expected from the macronutrients.
+    pub fn estimated_energy(&self) -> f64 {
+        ATWATER_FACTORS
+            .iter()
+            .map(|(nutrient, factor)| self.0.get(nutrient).copied().unwrap_or(0.0) * factor)
+            .sum()
+    }
+
+    /// Same facts with energy estimated from the macronutrients unless it is
+    /// already declared.
+    pub fn with_estimated_energy(mut self) -> NutritionFacts {
+        if !self.0.contains_key(&Nutrition::Energy) {
+            let energy = self.estimated_energy();
+            self.0.insert(Nutrition::Energy, energy);
+        }
+        self
+    }
+
+    /// Difference between the declared and the estimated energy relative to
+    /// the declared one, or to the estimated one when no energy is declared,
+    /// `None` unless both energy and some macronutrient are declared.
+    pub fn energy_discrepancy(&self) -> Option<f64> {
+        let declared = *self.0.get(&Nutrition::Energy)?;
+        if !ATWATER_FACTORS
+            .iter()
+            .any(|(nutrient, _)| self.0.contains_key(nutrient))
+        {
+            return None;
+        }
+        let estimated = self.estimated_energy();
+        let difference = (declared - estimated).abs();
+        if difference == 0.0 {
+            Some(0.0)
+        } else if declared == 0.0 {
+            Some(difference / estimated)
+        } else {
+            Some(difference / declared)
+        }
+    }
+
+    /// Shares of energy from macronutrients summing to 100, or all zeros
+    /// when the macronutrients provide no energy.
+    pub fn macro_ratios(&self) -> MacroRatios {
+        let [proteins, fats, carbohydrates] = ATWATER_FACTORS
+            .map(|(nutrient, factor)| self.0.get(&nutrient).copied().unwrap_or(0.0) * factor);
+        let total = proteins + fats + carbohydrates;
+        if total <= 0.0 {
+            return MacroRatios::default();
+        }
+        MacroRatios {
+            proteins: proteins / total * 100.0,
+            fats: fats / total * 100.0,
+            carbohydrates: carbohydrates / total * 100.0,
+        }
+    }
+
+    /// Amounts of macronutrients per 100 kcal of the facts, `None` when the
+    /// facts provide no energy to relate them to.
+    pub fn per_energy(&self) -> Option<NutritionFacts> {
+        let energy = self.get(Nutrition::Energy).filter(|energy| *energy > 0.0)?;
+        Some(
+            self.iter()
+                .filter(|(nutrient, _)| {
+                    **nutrient != Nutrition::Energy
+                        && nutrient.group() == NutrientGroup::Macronutrients
+                })
+                .map(|(nutrient, amount)| (nutrient.clone(), amount / energy * 100.0))
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Display for MacroRatios {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Protein {:.0}% / Fat {:.0}% / Carbs {:.0}%",
+            self.proteins, self.fats, self.carbohydrates
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MacroRatios, ENERGY_DISCREPANCY_THRESHOLD};
+    use crate::{Nutrition, NutritionFacts};
+
+    #[test]
+    fn macro_ratios() {
+        let facts = NutritionFacts::from_iter([
+            (Nutrition::Energy, 160.0),
+            (Nutrition::Proteins, 10.0),
+            (Nutrition::Fats, 8.0),
+            (Nutrition::Carbohydrates, 12.0),
+        ]);
+
+        let ratios = facts.macro_ratios();
+
+        assert!((ratios.proteins + ratios.fats + ratios.carbohydrates - 100.0).abs() < 1e-9);
+        assert_eq!(ratios.to_string(), "Protein 25% / Fat 45% / Carbs 30%");
+    }
+
+    #[test]
+    fn per_energy() {
+        let facts = NutritionFacts::from_iter([
+            (Nutrition::Energy, 200.0),
+            (Nutrition::Proteins, 10.0),
+            (Nutrition::Fats, 4.0),
+            (Nutrition::Sodium, 300.0),
+        ]);
+
+        let per_energy = facts.per_energy().unwrap();
+
+        assert_eq!(
+            per_energy,
+            NutritionFacts::from_iter([(Nutrition::Proteins, 5.0), (Nutrition::Fats, 2.0)])
+        );
+        assert_eq!(
+            NutritionFacts::from_iter([(Nutrition::Energy, 0.0), (Nutrition::Water, 90.0)])
+                .per_energy(),
+            None
+        );
+    }
+
+    #[test]
+    fn macro_ratios_without_energy() {
+        let facts = NutritionFacts::from_iter([(Nutrition::Sodium, 10.0)]);
+
+        assert_eq!(facts.macro_ratios(), MacroRatios::default());
+    }
+
+    #[test]
+    fn energy_discrepancy() {
+        let consistent = NutritionFacts::from_iter([
+            (Nutrition::Energy, 165.0),
+            (Nutrition::Proteins, 10.0),
+            (Nutrition::Fats, 8.0),
+            (Nutrition::Carbohydrates, 12.0),
+        ]);
+        let mistyped = NutritionFacts::from_iter([
+            (Nutrition::Energy, 16.0),
+            (Nutrition::Proteins, 10.0),
+            (Nutrition::Fats, 8.0),
+            (Nutrition::Carbohydrates, 12.0),
+        ]);
+        let zero = NutritionFacts::from_iter([(Nutrition::Energy, 0.0), (Nutrition::Fats, 8.0)]);
+        let undeclared = NutritionFacts::from_iter([(Nutrition::Proteins, 10.0)]);
+        let energy_only = NutritionFacts::from_iter([(Nutrition::Energy, 100.0)]);
+
+        assert_eq!(consistent.estimated_energy(), 160.0);
+        assert!(consistent.energy_discrepancy().unwrap() < ENERGY_DISCREPANCY_THRESHOLD);
+        assert_eq!(mistyped.energy_discrepancy(), Some(9.0));
+        assert_eq!(zero.energy_discrepancy(), Some(1.0));
+        assert_eq!(undeclared.energy_discrepancy(), None);
+        assert_eq!(energy_only.energy_discrepancy(), None);
+    }
+}
@@ -0,0 +1,445 @@
+use std::{
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum RecipeError {
+    IngredientNotFound {
+        ingredient: String,
+        available: Vec<String>,
+        /// Available product with a name close to the ingredient's.
+        suggestion: Option<String>,
+    },
+    CycleDetected {
+        path: PathBuf,
+    },
+    /// A referenced file is in none of the searched locations.
+    FileNotFound {
+        path: PathBuf,
+        searched: Vec<PathBuf>,
+    },
+    MaxDepthExceeded {
+        depth: usize,
+        path: PathBuf,
+    },
+    MissingServings,
+    /// The dish declares no weight while weights are required.
+    MissingWeight,
+    /// An ingredient weighs implausibly more than its dish is declared to.
+    OverweightIngredient {
+        ingredient: String,
+        grams: f64,
+        dish_weight: f64,
+    },
+    /// The dish weighs nothing, so facts per 100g can't be computed.
+    ZeroWeight,
+    DuplicateProduct {
+        name: String,
+    },
+    InvalidValue {
+        field: String,
+        value: f64,
+    },
+    UnknownUnit {
+        ingredient: String,
+        unit: String,
+    },
+    MissingDensity {
+        product: String,
+    },
+    MissingPieceWeight {
+        product: String,
+    },
+    MissingServingWeight {
+        product: String,
+    },
+    MissingPrice {
+        product: String,
+    },
+    /// Whole dishes of a sub-recipe are counted where they'd have to be
+    /// weighed before the sub-recipe is computed.
+    UnknownRecipeWeight {
+        product: String,
+    },
+    /// An ingredient is given in percent of a dish that declares no weight.
+    MissingDishWeight {
+        ingredient: String,
+    },
+    /// A blend is made of itself, with the chain of blends leading back to
+    /// it, outermost first.
+    BlendCycle {
+        chain: Vec<String>,
+    },
+    /// Percentages of a blend's components don't add up to 100.
+    BlendPercentage {
+        product: String,
+        total: f64,
+    },
+    /// A product gives both `per` and `basis`.
+    ConflictingBasis {
+        product: String,
+    },
+    /// Facts of a product made by a recipe or a blend are needed without
+    /// the recipe it's part of.
+    UnresolvedProduct {
+        product: String,
+    },
+    /// Fields no recipe has were found while parsing strictly.
+    UnknownFields {
+        fields: Vec<String>,
+    },
+    /// A recipe that may not refer to files includes a library or is made
+    /// of a sub-recipe.
+    FileReference {
+        path: PathBuf,
+    },
+    /// Error in a sub-recipe, with the chain of recipe files leading to it,
+    /// outermost first.
+    InRecipe {
+        chain: Vec<PathBuf>,
+        source: Box<RecipeError>,
+    },
+    /// Error computing the facts of a product on its own.
+    InProduct {
+        product: String,
+        source: Box<RecipeError>,
+    },
+    Io(io::Error),
+    /// Malformed YAML, with the file it was read from when known.
+    Parse {
+        path: Option<PathBuf>,
+        source: serde_yaml::Error,
+    },
+    ParseJson(serde_json::Error),
+}
+
+impl RecipeError {
+    /// Exit codes of the CLI, distinct for every kind of error.
+    pub const EXIT_CODES: &'static [(&'static str, i32)] = &[
+        ("ingredient_not_found", 10),
+        ("cycle_detected", 11),
+        ("file_not_found", 12),
+        ("max_depth_exceeded", 13),
+        ("missing_servings", 14),
+        ("missing_weight", 15),
+        ("overweight_ingredient", 16),
+        ("zero_weight", 17),
+        ("duplicate_product", 18),
+        ("invalid_value", 19),
+        ("unknown_unit", 20),
+        ("missing_density", 21),
+        ("missing_piece_weight", 22),
+        ("missing_serving_weight", 23),
+        ("missing_price", 24),
+        ("unknown_recipe_weight", 25),
+        ("missing_dish_weight", 26),
+        ("blend_percentage", 27),
+        ("conflicting_basis", 28),
+        ("unknown_fields", 29),
+        ("io", 30),
+        ("parse", 31),
+        ("parse_json", 32),
+        ("unresolved_product", 33),
+        ("file_reference", 34),
+        ("blend_cycle", 35),
+    ];
+
+    /// The error itself, or the one of the sub-recipe or product it came
+    /// from.
+    pub fn root_cause(&self) -> &RecipeError {
+        match self {
+            RecipeError::InRecipe { source, .. } | RecipeError::InProduct { source, .. } => {
+                source.root_cause()
+            }
+            err => err,
+        }
+    }
+
+    /// Name of the kind of the root cause, like `file_not_found`, for
+    /// tools telling errors apart.
+    pub fn kind(&self) -> &'static str {
+        match self.root_cause() {
+            RecipeError::IngredientNotFound { .. } => "ingredient_not_found",
+            RecipeError::CycleDetected { .. } => "cycle_detected",
+            RecipeError::FileNotFound { .. } => "file_not_found",
+            RecipeError::MaxDepthExceeded { .. } => "max_depth_exceeded",
+            RecipeError::MissingServings => "missing_servings",
+            RecipeError::MissingWeight => "missing_weight",
+            RecipeError::OverweightIngredient { .. } => "overweight_ingredient",
+            RecipeError::ZeroWeight => "zero_weight",
+            RecipeError::DuplicateProduct { .. } => "duplicate_product",
+            RecipeError::InvalidValue { .. } => "invalid_value",
+            RecipeError::UnknownUnit { .. } => "unknown_unit",
+            RecipeError::MissingDensity { .. } => "missing_density",
+            RecipeError::MissingPieceWeight { .. } => "missing_piece_weight",
+            RecipeError::MissingServingWeight { .. } => "missing_serving_weight",
+            RecipeError::MissingPrice { .. } => "missing_price",
+            RecipeError::UnknownRecipeWeight { .. } => "unknown_recipe_weight",
+            RecipeError::MissingDishWeight { .. } => "missing_dish_weight",
+            RecipeError::BlendPercentage { .. } => "blend_percentage",
+            RecipeError::ConflictingBasis { .. } => "conflicting_basis",
+            RecipeError::UnknownFields { .. } => "unknown_fields",
+            RecipeError::FileReference { .. } => "file_reference",
+            RecipeError::BlendCycle { .. } => "blend_cycle",
+            RecipeError::UnresolvedProduct { .. } => "unresolved_product",
+            RecipeError::Io(_) => "io",
+            RecipeError::Parse { .. } => "parse",
+            RecipeError::ParseJson(_) => "parse_json",
+            RecipeError::InRecipe { .. } | RecipeError::InProduct { .. } => {
+                unreachable!("root causes are never wrapped")
+            }
+        }
+    }
+
+    /// Exit code of the CLI failing with the error, the one
+    /// [`RecipeError::EXIT_CODES`] gives the kind of the root cause.
+    pub fn exit_code(&self) -> i32 {
+        let kind = self.kind();
+        RecipeError::EXIT_CODES
+            .iter()
+            .find(|&&(listed, _)| listed == kind)
+            .map(|&(_, code)| code)
+            .unwrap_or_else(|| unreachable!("{} has no exit code", kind))
+    }
+
+    /// The error with `path` as the file YAML that failed to parse was read
+    /// from, unless it already names one.
+    pub(crate) fn in_file(self, path: &Path) -> Self {
+        match self {
+            RecipeError::Parse { path: None, source } => RecipeError::Parse {
+                path: Some(path.to_path_buf()),
+                source,
+            },
+            err => err,
+        }
+    }
+}
+
+impl fmt::Display for RecipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecipeError::IngredientNotFound {
+                ingredient,
+                available,
+                suggestion,
+            } => {
+                write!(
+                    f,
+                    "Cannot find ingredient in recipe: {} possible products: {}",
+                    ingredient,
+                    available.join(", ")
+                )?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, ", did you mean '{}'?", suggestion)?;
+                }
+                Ok(())
+            }
+            RecipeError::CycleDetected { path } => {
+                write!(f, "Recipe references itself: {}", path.to_string_lossy())
+            }
+            RecipeError::BlendCycle { chain } => {
+                write!(f, "Blend is made of itself: {}", chain.join(" -> "))
+            }
+            RecipeError::FileNotFound { path, searched } => write!(
+                f,
+                "Cannot find {}, searched: {}",
+                path.to_string_lossy(),
+                searched
+                    .iter()
+                    .map(|path| path.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            RecipeError::MaxDepthExceeded { depth, path } => write!(
+                f,
+                "Sub-recipes are nested deeper than {} levels at: {}",
+                depth,
+                path.to_string_lossy()
+            ),
+            RecipeError::MissingServings => {
+                write!(f, "Dish doesn't declare how many servings it has")
+            }
+            RecipeError::MissingWeight => write!(f, "Dish doesn't declare its weight"),
+            RecipeError::OverweightIngredient {
+                ingredient,
+                grams,
+                dish_weight,
+            } => write!(
+                f,
+                "Ingredient {} weighs {}g, more than the whole dish of {}g",
+                ingredient, grams, dish_weight
+            ),
+            RecipeError::ZeroWeight => write!(f, "Dish weighs nothing"),
+            RecipeError::DuplicateProduct { name } => {
+                write!(f, "Product is defined more than once: {}", name)
+            }
+            RecipeError::InvalidValue { field, value } => {
+                write!(f, "Invalid value of {}: {}", field, value)
+            }
+            RecipeError::ConflictingBasis { product } => {
+                write!(f, "Product {} gives both per and basis", product)
+            }
+            RecipeError::UnknownFields { fields } => {
+                write!(f, "Unknown fields: {}", fields.join(", "))
+            }
+            RecipeError::FileReference { path } => write!(
+                f,
+                "Recipe refers to {}, while it may not refer to files",
+                path.to_string_lossy()
+            ),
+            RecipeError::UnresolvedProduct { product } => write!(
+                f,
+                "Product {} is made from other products and can't be computed on its own",
+                product
+            ),
+            RecipeError::UnknownUnit { ingredient, unit } => {
+                write!(f, "Unknown unit of ingredient {}: {}", ingredient, unit)
+            }
+            RecipeError::MissingDensity { product } => write!(
+                f,
+                "Product {} is measured by volume but has no density",
+                product
+            ),
+            RecipeError::MissingPieceWeight { product } => write!(
+                f,
+                "Product {} is counted in pieces but has no grams_per_piece",
+                product
+            ),
+            RecipeError::MissingServingWeight { product } => write!(
+                f,
+                "Product {} is measured in servings but has no serving_weight_g",
+                product
+            ),
+            RecipeError::MissingPrice { product } => {
+                write!(f, "Product {} has no price", product)
+            }
+            RecipeError::UnknownRecipeWeight { product } => write!(
+                f,
+                "Product {} counts whole dishes, whose weight isn't known before computing them",
+                product
+            ),
+            RecipeError::MissingDishWeight { ingredient } => write!(
+                f,
+                "Ingredient {} is given in percent of the dish weight but the dish has no weight",
+                ingredient
+            ),
+            RecipeError::BlendPercentage { product, total } => write!(
+                f,
+                "Percentages of blend {} add up to {} instead of 100",
+                product, total
+            ),
+            RecipeError::InRecipe { chain, source } => {
+                let chain: Vec<_> = chain
+                    .iter()
+                    .map(|path| {
+                        path.file_name()
+                            .unwrap_or(path.as_os_str())
+                            .to_string_lossy()
+                    })
+                    .collect();
+                write!(f, "in {}: {}", chain.join(" -> "), source)
+            }
+            RecipeError::InProduct { product, source } => {
+                write!(f, "in product {}: {}", product, source)
+            }
+            RecipeError::Io(err) => write!(f, "{}", err),
+            RecipeError::Parse {
+                path: Some(path),
+                source,
+            } => match source.location() {
+                // The location leads like in compiler messages instead of
+                // trailing the message.
+                Some(location) => write!(
+                    f,
+                    "{}:{}:{}: {}",
+                    path.to_string_lossy(),
+                    location.line(),
+                    location.column(),
+                    source.to_string().replacen(
+                        &format!(" at line {} column {}", location.line(), location.column()),
+                        "",
+                        1
+                    )
+                ),
+                None => write!(f, "{}: {}", path.to_string_lossy(), source),
+            },
+            RecipeError::Parse { path: None, source } => write!(f, "{}", source),
+            RecipeError::ParseJson(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for RecipeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RecipeError::InRecipe { source, .. } | RecipeError::InProduct { source, .. } => {
+                Some(source)
+            }
+            RecipeError::Io(err) => Some(err),
+            RecipeError::Parse { source, .. } => Some(source),
+            RecipeError::ParseJson(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RecipeError {
+    fn from(err: io::Error) -> Self {
+        RecipeError::Io(err)
+    }
+}
+
+impl From<serde_yaml::Error> for RecipeError {
+    fn from(err: serde_yaml::Error) -> Self {
+        RecipeError::Parse {
+            path: None,
+            source: err,
+        }
+    }
+}
+
+impl From<serde_json::Error> for RecipeError {
+    fn from(err: serde_json::Error) -> Self {
+        RecipeError::ParseJson(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::RecipeError;
+
+    #[test]
+    fn tell_kinds_of_root_causes() {
+        let missing = RecipeError::FileNotFound {
+            path: "cake.yaml".into(),
+            searched: Vec::new(),
+        };
+        let nested = RecipeError::InRecipe {
+            chain: vec![PathBuf::from("dinner.yaml")],
+            source: Box::new(RecipeError::IngredientNotFound {
+                ingredient: "Oil".into(),
+                available: Vec::new(),
+                suggestion: None,
+            }),
+        };
+
+        assert_eq!(missing.kind(), "file_not_found");
+        assert_eq!(nested.kind(), "ingredient_not_found");
+        assert_ne!(missing.exit_code(), nested.exit_code());
+        assert_eq!(nested.exit_code(), 10);
+    }
+
+    #[test]
+    fn list_distinct_exit_codes() {
+        let codes = RecipeError::EXIT_CODES;
+
+        for (i, (kind, code)) in codes.iter().enumerate() {
+            assert!(codes[..i].iter().all(|(k, c)| k != kind && c != code));
+            assert!(*code >= 10, "{} exits like other failures", kind);
+        }
+    }
+}
@@ -0,0 +1,720 @@
+//! Renderers of computed nutrition facts for the CLI output formats.
+
+use std::{fmt::Write, iter};
+
+use crate::{
+    compare::NutrientDelta,
+    daily_values::DailyValues,
+    range::NutritionRange,
+    recipe::{Explanation, NutrientGroup, Nutrition, NutritionFacts},
+};
+
+/// Decimal places of amounts unless asked otherwise.
+pub const DEFAULT_PRECISION: usize = 2;
+
+/// How the renderers lay out amounts.
+#[derive(Clone, Debug)]
+pub struct Style {
+    /// Decimal places of amounts.
+    pub precision: usize,
+    /// Nutrients listed first and in this order, the rest follow in display
+    /// order.
+    pub order: Vec<Nutrition>,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            precision: DEFAULT_PRECISION,
+            order: Nutrition::KNOWN.to_vec(),
+        }
+    }
+}
+
+impl Style {
+    fn items<'a>(&self, facts: &'a NutritionFacts) -> Vec<(&'a Nutrition, f64)> {
+        facts.sorted_by(&self.order)
+    }
+}
+
+/// Rows of every group that has any, in the order of [`NutrientGroup::ALL`],
+/// rows keeping their order within a group.
+fn grouped<T>(rows: &[T], group: impl Fn(&T) -> NutrientGroup) -> Vec<(NutrientGroup, Vec<&T>)> {
+    NutrientGroup::ALL
+        .into_iter()
+        .map(|g| {
+            (
+                g,
+                rows.iter()
+                    .filter(|row| group(row) == g)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .filter(|(_, rows)| !rows.is_empty())
+        .collect()
+}
+
+/// Renders facts as text, one `name:  amount unit` line per nutrient. This is
+/// what `Display` of [`NutritionFacts`] writes with the default order.
+pub fn text(facts: &NutritionFacts, style: &Style) -> String {
+    let mut out = String::new();
+    for (item, value) in style.items(facts) {
+        write_amount(&mut out, item, value, style);
+        out.push('\n');
+    }
+    out
+}
+
+/// Renders facts as text like [`text`], following amounts weighed in grams
+/// with their share of the `weight` in grams the facts are given for, like
+/// `Fats:  30.00 g (10.00%)` for 300g.
+pub fn weight_shares(facts: &NutritionFacts, weight: f64, style: &Style) -> String {
+    let mut out = String::new();
+    for (item, value) in style.items(facts) {
+        write_amount(&mut out, item, value, style);
+        if item.unit() == "g" && weight > 0.0 {
+            write!(out, " ({:.*}%)", style.precision, value / weight * 100.0)
+                .expect("writing to a String never fails");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Renders ranges as text like [`text`], with `min–max` amounts where the
+/// bounds differ at the precision.
+pub fn range(range: &NutritionRange, style: &Style) -> String {
+    let mut out = String::new();
+    for (item, max) in style.items(&range.max) {
+        let min = range.min.0.get(item).copied().unwrap_or(0.0);
+        let (min, max) = (
+            format!("{:.*}", style.precision, min),
+            format!("{:.*}", style.precision, max),
+        );
+        if min == max {
+            write!(out, "{}:  {}", item, min)
+        } else {
+            write!(out, "{}:  {}–{}", item, min, max)
+        }
+        .expect("writing to a String never fails");
+        if !item.unit().is_empty() {
+            write!(out, " {}", item.unit()).expect("writing to a String never fails");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn write_amount(out: &mut String, item: &Nutrition, value: f64, style: &Style) {
+    write!(out, "{}:  {:.*}", item, style.precision, value)
+        .expect("writing to a String never fails");
+    if !item.unit().is_empty() {
+        write!(out, " {}", item.unit()).expect("writing to a String never fails");
+    }
+}
+
+/// Renders facts related to energy, like [`NutritionFacts::per_energy`]
+/// gives them, as text with `name:  amount unit/100kcal` lines.
+pub fn per_energy(facts: &NutritionFacts, style: &Style) -> String {
+    let mut out = String::new();
+    for (item, value) in style.items(facts) {
+        write_amount(&mut out, item, value, style);
+        out.push_str("/100kcal\n");
+    }
+    out
+}
+
+/// Renders facts as CSV with a `nutrient,amount,unit` header, one row per
+/// present nutrient.
+pub fn csv(facts: &NutritionFacts, style: &Style) -> String {
+    let mut out = String::from("nutrient,amount,unit\n");
+    for (item, value) in style.items(facts) {
+        writeln!(
+            out,
+            "{},{:.*},{}",
+            csv_field(item.name()),
+            style.precision,
+            value,
+            item.unit()
+        )
+        .expect("writing to a String never fails");
+    }
+    out
+}
+
+/// Renders facts of several recipes as CSV with a
+/// `recipe,nutrient,amount,unit` header.
+pub fn csv_named(reports: &[(String, NutritionFacts)], style: &Style) -> String {
+    let mut out = String::from("recipe,nutrient,amount,unit\n");
+    for (name, facts) in reports {
+        for (item, value) in style.items(facts) {
+            writeln!(
+                out,
+                "{},{},{:.*},{}",
+                csv_field(name),
+                csv_field(item.name()),
+                style.precision,
+                value,
+                item.unit()
+            )
+            .expect("writing to a String never fails");
+        }
+    }
+    out
+}
+
+/// Renders facts as a bordered table with aligned nutrient, amount and unit
+/// columns, in a section per [`NutrientGroup`].
+pub fn table(facts: &NutritionFacts, style: &Style) -> String {
+    let rows: Vec<(&Nutrition, String)> = style
+        .items(facts)
+        .into_iter()
+        .map(|(item, value)| (item, format!("{:.*}", style.precision, value)))
+        .collect();
+    let groups = grouped(&rows, |(item, _)| item.group());
+    let title_width = groups
+        .iter()
+        .map(|(group, _)| group.title().len())
+        .fold("Nutrient".len(), usize::max);
+    let widths = rows.iter().fold(
+        (title_width, "Amount".len(), "Unit".len()),
+        |(name, amount, unit), (item, value)| {
+            (
+                name.max(item.name().chars().count()),
+                amount.max(value.len()),
+                unit.max(item.unit().len()),
+            )
+        },
+    );
+    let border = format!(
+        "+-{}-+-{}-+-{}-+\n",
+        "-".repeat(widths.0),
+        "-".repeat(widths.1),
+        "-".repeat(widths.2)
+    );
+    let mut out = border.clone();
+    table_row(&mut out, widths, ("Nutrient", "Amount", "Unit"));
+    out.push_str(&border);
+    for (group, rows) in groups {
+        table_row(&mut out, widths, (group.title(), "", ""));
+        for (item, value) in rows {
+            table_row(&mut out, widths, (item.name(), value, item.unit()));
+        }
+        out.push_str(&border);
+    }
+    out
+}
+
+fn table_row(out: &mut String, widths: (usize, usize, usize), cells: (&str, &str, &str)) {
+    writeln!(
+        out,
+        "| {:<w0$} | {:>w1$} | {:<w2$} |",
+        cells.0,
+        cells.1,
+        cells.2,
+        w0 = widths.0,
+        w1 = widths.1,
+        w2 = widths.2
+    )
+    .expect("writing to a String never fails");
+}
+
+/// Nutrients that are part of another one, indented under it on labels.
+const SUB_NUTRIENTS: [Nutrition; 2] = [Nutrition::Fiber, Nutrition::Sugars];
+
+/// Renders facts as a nutrition facts panel for a serving of `serving_weight`
+/// grams: energy between heavy rules, then the other nutrients in a section
+/// per [`NutrientGroup`], with the ones that are part of carbohydrates
+/// indented.
+pub fn label(facts: &NutritionFacts, serving_weight: f64, style: &Style) -> String {
+    let rows: Vec<(&Nutrition, String, String)> = style
+        .items(facts)
+        .into_iter()
+        .map(|(item, value)| {
+            let indent = if SUB_NUTRIENTS.contains(item) {
+                "  "
+            } else {
+                ""
+            };
+            (
+                item,
+                format!("{}{}", indent, item),
+                format!("{:.*}", style.precision, value),
+            )
+        })
+        .collect();
+    let widths = rows.iter().fold((0, 0, 0), |(name, amount, unit), row| {
+        (
+            name.max(row.1.chars().count()),
+            amount.max(row.2.len()),
+            unit.max(row.0.unit().len()),
+        )
+    });
+    let title = "Nutrition Facts";
+    let serving = format!("Serving size {:.0} g", serving_weight);
+    let width = NutrientGroup::ALL
+        .iter()
+        .map(|group| group.title().len())
+        .fold(widths.0 + 2 + widths.1 + 1 + widths.2, usize::max)
+        .max(title.len())
+        .max(serving.len());
+    let heavy_rule = "=".repeat(width);
+    let mut out = String::new();
+    writeln!(out, "{}\n{}\n{}", title, serving, heavy_rule)
+        .expect("writing to a String never fails");
+    let (energy, rest): (Vec<_>, Vec<_>) = rows
+        .iter()
+        .partition(|(item, _, _)| **item == Nutrition::Energy);
+    for row in energy {
+        label_row(&mut out, width, widths, row);
+        writeln!(out, "{}", heavy_rule).expect("writing to a String never fails");
+    }
+    for (group, rows) in grouped(&rest, |(item, _, _)| item.group()) {
+        writeln!(out, "{}", group.title()).expect("writing to a String never fails");
+        for row in rows {
+            label_row(&mut out, width, widths, row);
+        }
+        writeln!(out, "{}", "-".repeat(width)).expect("writing to a String never fails");
+    }
+    out
+}
+
+fn label_row(
+    out: &mut String,
+    width: usize,
+    widths: (usize, usize, usize),
+    (item, name, amount): &(&Nutrition, String, String),
+) {
+    let value = format!(
+        "{:>w1$} {:<w2$}",
+        amount,
+        item.unit(),
+        w1 = widths.1,
+        w2 = widths.2
+    );
+    let padding = width - name.chars().count();
+    let row = format!("{}{:>w$}", name, value, w = padding);
+    writeln!(out, "{}", row.trim_end()).expect("writing to a String never fails");
+}
+
+/// Nutrients shown by [`summary`].
+const SUMMARY_NUTRIENTS: [Nutrition; 4] = [
+    Nutrition::Energy,
+    Nutrition::Proteins,
+    Nutrition::Fats,
+    Nutrition::Carbohydrates,
+];
+
+/// Renders facts of several recipes as a table with a line per recipe and a
+/// column for each of energy and macronutrients, `-` when one is missing.
+pub fn summary(reports: &[(String, NutritionFacts)], style: &Style) -> String {
+    named_table("Recipe", reports, style)
+}
+
+/// Renders the steps of computing facts as text: every ingredient with the
+/// grams its amount weighs, the facts per 100g of its product and what it
+/// adds, the sum of the additions, and the facts per 100g of the dish they
+/// are normalized to.
+pub fn explanation(explanation: &Explanation, style: &Style) -> String {
+    let amount = |value: f64| format!("{:.*} g", style.precision, value);
+    let mut out = String::new();
+    for step in &explanation.ingredients {
+        let written = match (&step.unit, step.to_taste) {
+            (_, true) => "to taste".to_string(),
+            (Some(unit), _) if unit != "g" => {
+                format!("{} {} = {}", step.amount, unit, amount(step.grams))
+            }
+            _ => amount(step.grams),
+        };
+        writeln!(out, "{}: {}", step.product, written).expect("writing to a String never fails");
+        if let Some(facts) = &step.facts {
+            writeln!(out, "  per 100g:  {}", inline(facts, style))
+                .expect("writing to a String never fails");
+        }
+        writeln!(out, "  adds:  {}", inline(&step.contribution, style))
+            .expect("writing to a String never fails");
+    }
+    let computed = &explanation.computed;
+    writeln!(
+        out,
+        "Total of {} raw:  {}",
+        amount(computed.raw_weight),
+        inline(&explanation.totals, style)
+    )
+    .expect("writing to a String never fails");
+    writeln!(
+        out,
+        "Per 100g of the dish of {}, the total times {:.*}:",
+        amount(computed.dish_weight),
+        style.precision + 2,
+        100.0 / computed.dish_weight
+    )
+    .expect("writing to a String never fails");
+    out.push_str(&text(&computed.facts, style));
+    out
+}
+
+/// Amounts of `facts` on one line, separated by commas.
+fn inline(facts: &NutritionFacts, style: &Style) -> String {
+    if facts.0.is_empty() {
+        return "nothing".into();
+    }
+    style
+        .items(facts)
+        .into_iter()
+        .map(|(item, value)| {
+            let mut out = String::new();
+            write_amount(&mut out, item, value, style);
+            out.replacen(":  ", " ", 1)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Renders what every ingredient contributes as a table like
+/// [`summary`], with a line per ingredient.
+pub fn breakdown(contributions: &[(String, NutritionFacts)], style: &Style) -> String {
+    named_table("Ingredient", contributions, style)
+}
+
+fn named_table(title: &str, reports: &[(String, NutritionFacts)], style: &Style) -> String {
+    let header = iter::once(title.to_string()).chain(
+        SUMMARY_NUTRIENTS
+            .iter()
+            .map(|nutrient| format!("{}, {}", nutrient, nutrient.unit())),
+    );
+    let mut lines: Vec<Vec<String>> = vec![header.collect()];
+    for (name, facts) in reports {
+        let amounts = SUMMARY_NUTRIENTS.iter().map(|nutrient| {
+            facts.get(nutrient.clone()).map_or_else(
+                || "-".into(),
+                |value| format!("{:.*}", style.precision, value),
+            )
+        });
+        lines.push(iter::once(name.clone()).chain(amounts).collect());
+    }
+    let widths: Vec<usize> = (0..=SUMMARY_NUTRIENTS.len())
+        .map(|column| {
+            lines
+                .iter()
+                .map(|line| line[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut out = String::new();
+    for line in &lines {
+        write!(out, "{:<w$}", line[0], w = widths[0]).expect("writing to a String never fails");
+        for (cell, width) in line.iter().zip(&widths).skip(1) {
+            write!(out, "  {:>w$}", cell, w = width).expect("writing to a String never fails");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Renders facts as text with the share of the daily intake next to every
+/// nutrient that has a reference value.
+pub fn daily_values(facts: &NutritionFacts, dv: &DailyValues, style: &Style) -> String {
+    let percentages = facts.daily_value_percentages(dv);
+    let mut out = String::new();
+    for (item, value) in style.items(facts) {
+        write_amount(&mut out, item, value, style);
+        if let Some(percentage) = percentages.get(item) {
+            write!(out, "  {:.0}% DV", percentage).expect("writing to a String never fails");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Renders compared amounts as text, one nutrient per line with both
+/// amounts and the signed delta, in percent too when it is defined.
+pub fn comparison(deltas: &[NutrientDelta], style: &Style) -> String {
+    let precision = style.precision;
+    let mut out = String::new();
+    for delta in deltas {
+        let unit = delta.nutrient.unit();
+        let unit = if unit.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", unit)
+        };
+        write!(
+            out,
+            "{}:  {:.*}{unit} vs {:.*}{unit}  {:+.*}{unit}",
+            delta.nutrient,
+            precision,
+            delta.left,
+            precision,
+            delta.right,
+            precision,
+            delta.delta(),
+            unit = unit
+        )
+        .expect("writing to a String never fails");
+        if let Some(percentage) = delta.percentage() {
+            write!(out, " ({:+.1}%)", percentage).expect("writing to a String never fails");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Style;
+    use crate::{Nutrition, NutritionFacts, Recipe};
+
+    #[tokio::test]
+    async fn csv() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Cheese
+    facts:
+      Sodium: 620
+      Fats: 27
+      Energy: 350
+      "calcium, total": 700
+dish:
+  ingredients:
+    - product: Cheese
+      amount: 100
+"#,
+        )
+        .unwrap();
+
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+
+        assert_eq!(
+            super::csv(&facts, &Style::default()),
+            "nutrient,amount,unit\n\
+             Energy,350.00,kcal\n\
+             Fats,27.00,g\n\
+             Sodium,620.00,mg\n\
+             \"calcium, total\",700.00,\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn table() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Cheese
+    facts:
+      Sodium: 620
+      Fats: 27
+      Energy: 350
+dish:
+  ingredients:
+    - product: Cheese
+      amount: 100
+"#,
+        )
+        .unwrap();
+
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+
+        assert_eq!(
+            super::table(&facts, &Style::default()),
+            "+----------------+--------+------+\n\
+             | Nutrient       | Amount | Unit |\n\
+             +----------------+--------+------+\n\
+             | Macronutrients |        |      |\n\
+             | Energy         | 350.00 | kcal |\n\
+             | Fats           |  27.00 | g    |\n\
+             +----------------+--------+------+\n\
+             | Micronutrients |        |      |\n\
+             | Sodium         | 620.00 | mg   |\n\
+             +----------------+--------+------+\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn text_in_custom_order() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Cheese
+    facts:
+      Sodium: 620
+      Proteins: 25
+      Fats: 27
+      Energy: 350
+      Calcium: 700
+dish:
+  ingredients:
+    - product: Cheese
+      amount: 100
+"#,
+        )
+        .unwrap();
+        let style = Style {
+            precision: 0,
+            order: vec![Nutrition::Energy, Nutrition::Fats, Nutrition::Sodium],
+        };
+
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+
+        assert_eq!(
+            super::text(&facts, &style),
+            "Energy:  350 kcal\n\
+             Fats:  27 g\n\
+             Sodium:  620 mg\n\
+             Proteins:  25 g\n\
+             Calcium:  700\n"
+        );
+    }
+
+    #[test]
+    fn weight_shares() {
+        let facts = NutritionFacts::from_iter([
+            (Nutrition::Energy, 1200.0),
+            (Nutrition::Fats, 30.0),
+            (Nutrition::Proteins, 4.5),
+            (Nutrition::Sodium, 900.0),
+        ]);
+
+        assert_eq!(
+            super::weight_shares(
+                &facts,
+                300.0,
+                &Style {
+                    precision: 1,
+                    ..Style::default()
+                }
+            ),
+            "Energy:  1200.0 kcal\n\
+             Proteins:  4.5 g (1.5%)\n\
+             Fats:  30.0 g (10.0%)\n\
+             Sodium:  900.0 mg\n"
+        );
+    }
+
+    #[test]
+    fn per_energy() {
+        let facts = NutritionFacts::from_iter([(Nutrition::Proteins, 5.0), (Nutrition::Fats, 2.5)]);
+
+        assert_eq!(
+            super::per_energy(
+                &facts,
+                &Style {
+                    precision: 1,
+                    ..Style::default()
+                }
+            ),
+            "Proteins:  5.0 g/100kcal\nFats:  2.5 g/100kcal\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn explanation() {
+        let recipe = crate::Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Oil
+    density_g_per_ml: 0.92
+    facts:
+      Energy: 900
+      Fats: 100
+  - name: Bread
+    facts:
+      Energy: 250
+      Proteins: 8
+dish:
+  weight: 200
+  ingredients:
+    - product: Oil
+      amount: 25
+      unit: ml
+    - product: Bread
+      amount: 177
+"#,
+        )
+        .unwrap();
+
+        let explanation = recipe.explain().await.unwrap();
+
+        assert_eq!(
+            super::explanation(
+                &explanation,
+                &Style {
+                    precision: 1,
+                    ..Style::default()
+                }
+            ),
+            "Oil: 25 ml = 23.0 g\n\
+             \x20 per 100g:  Energy 900.0 kcal, Fats 100.0 g\n\
+             \x20 adds:  Energy 207.0 kcal, Fats 23.0 g\n\
+             Bread: 177.0 g\n\
+             \x20 per 100g:  Energy 250.0 kcal, Proteins 8.0 g\n\
+             \x20 adds:  Energy 442.5 kcal, Proteins 14.2 g\n\
+             Total of 200.0 g raw:  Energy 649.5 kcal, Proteins 14.2 g, Fats 23.0 g\n\
+             Per 100g of the dish of 200.0 g, the total times 0.500:\n\
+             Energy:  324.8 kcal\n\
+             Proteins:  7.1 g\n\
+             Fats:  11.5 g\n"
+        );
+    }
+
+    #[test]
+    fn label() {
+        let facts = NutritionFacts::from_iter([
+            (Nutrition::Energy, 312.5),
+            (Nutrition::Proteins, 9.0),
+            (Nutrition::Carbohydrates, 48.0),
+            (Nutrition::Fiber, 7.0),
+            (Nutrition::Sodium, 500.0),
+        ]);
+
+        assert_eq!(
+            super::label(&facts, 125.0, &Style::default()),
+            "Nutrition Facts\n\
+             Serving size 125 g\n\
+             ==========================\n\
+             Energy         312.50 kcal\n\
+             ==========================\n\
+             Macronutrients\n\
+             Proteins         9.00 g\n\
+             Carbohydrates   48.00 g\n\
+             \x20 Fiber          7.00 g\n\
+             --------------------------\n\
+             Micronutrients\n\
+             Sodium         500.00 mg\n\
+             --------------------------\n"
+        );
+    }
+
+    #[test]
+    fn summary() {
+        let kimchi = NutritionFacts::from_iter([
+            (Nutrition::Energy, 31.4),
+            (Nutrition::Proteins, 1.6),
+            (Nutrition::Carbohydrates, 6.6),
+        ]);
+        let oil = NutritionFacts::from_iter([(Nutrition::Energy, 900.0), (Nutrition::Fats, 100.0)]);
+
+        assert_eq!(
+            super::summary(
+                &[("kimchi.yaml".into(), kimchi), ("oil.yaml".into(), oil)],
+                &Style {
+                    precision: 1,
+                    ..Style::default()
+                }
+            ),
+            "Recipe       Energy, kcal  Proteins, g  Fats, g  Carbohydrates, g\n\
+             kimchi.yaml          31.4          1.6        -               6.6\n\
+             oil.yaml            900.0            -    100.0                 -\n"
+        );
+    }
+}
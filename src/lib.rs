@@ -0,0 +1,36 @@
+//! Nutrition facts calculation for recipes described in YAML files.
+
+mod accumulator;
+mod compare;
+mod constraints;
+mod daily_values;
+mod energy;
+mod error;
+pub mod format;
+mod meal_plan;
+mod range;
+mod recipe;
+mod schema;
+#[cfg(feature = "serve")]
+pub mod serve;
+mod timings;
+mod unit;
+
+pub use accumulator::NutritionAccumulator;
+pub use compare::NutrientDelta;
+pub use constraints::{Constraints, Limits, Violation};
+pub use daily_values::DailyValues;
+pub use energy::{MacroRatios, ENERGY_DISCREPANCY_THRESHOLD};
+pub use error::RecipeError;
+pub use meal_plan::{MealFacts, MealPlan, MealPlanFacts};
+pub use range::{Bound, NutritionRange};
+pub use recipe::{
+    default_database_path, Basis, BlendComponent, ComputeOptions, ComputedRecipe, Cost, Dish,
+    Explanation, FactsBasis, Ingredient, IngredientFilter, IngredientStep, InputFormat, Metadata,
+    NutrientGroup, Nutrition, NutritionData, NutritionFacts, ParseOptions, Product, Recipe,
+    RecipeBasis, RecipeBuilder, RoundingPolicy, SubtractPolicy, CANONICAL_PRECISION,
+    CONFIG_DIR_VAR, DEFAULT_MAX_DEPTH, OVERWEIGHT_INGREDIENT_RATIO, SEARCH_PATH_VAR,
+};
+pub use schema::recipe_schema;
+pub use timings::Timings;
+pub use unit::Unit;
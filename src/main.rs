@@ -1,25 +1,1013 @@
-use std::{error::Error, path::PathBuf};
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use clap::Parser;
-use recipe::Recipe;
-mod recipe;
+use futures::future::{join_all, try_join_all};
+use notify::{RecursiveMode, Watcher};
+use nutritions::{
+    format::{self, Style},
+    Bound, ComputeOptions, Constraints, DailyValues, Explanation, IngredientFilter, InputFormat,
+    MealPlan, Nutrition, NutritionFacts, NutritionRange, ParseOptions, Recipe, RecipeError,
+    RoundingPolicy, Timings, Violation, DEFAULT_MAX_DEPTH, ENERGY_DISCREPANCY_THRESHOLD,
+};
+use owo_colors::OwoColorize;
+use serde::{Serialize, Serializer};
+use tokio::io::AsyncReadExt;
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+    Table,
+    /// Facts usable as the `facts` of a product.
+    Yaml,
+    /// Nutrition facts panel of a serving, or of 100g when the recipe
+    /// declares no servings.
+    Label,
+}
+
+#[cfg(feature = "serve")]
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Answers `POST /facts` requests carrying a recipe with its facts per
+    /// 100g as JSON, computed as the options ask.
+    Serve {
+        /// Address to listen on.
+        #[clap(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+    },
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum ErrorFormat {
+    Text,
+    /// An object with the `kind` of the error, its exit `code` and its
+    /// `message`.
+    Json,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum Rounding {
+    /// Amounts as computed.
+    Exact,
+    /// Energy to whole kcal, sodium to whole mg and the rest to 0.1g.
+    Label,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum ColorChoice {
+    /// Colors when printing to a terminal and `NO_COLOR` isn't set.
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+impl From<Rounding> for RoundingPolicy {
+    fn from(rounding: Rounding) -> Self {
+        match rounding {
+            Rounding::Exact => RoundingPolicy::Exact,
+            Rounding::Label => RoundingPolicy::Label,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum FormatIn {
+    Yaml,
+    Json,
+}
+
+impl From<FormatIn> for InputFormat {
+    fn from(format: FormatIn) -> Self {
+        match format {
+            FormatIn::Yaml => InputFormat::Yaml,
+            FormatIn::Json => InputFormat::Json,
+        }
+    }
+}
+
+/// Time the changes of a file are given to settle before running again.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(100);
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum Per {
+    #[value(name = "100g")]
+    HundredGrams,
+    Serving,
+    Total,
+}
+
+/// Exit codes of failures, listed by `--help`.
+fn exit_codes() -> String {
+    let mut help = "\
+Exit codes:
+  0   success
+  1   failures other than errors in recipes, like violated constraints
+  2   invalid arguments
+  10 and up, one for every kind of error in recipes:"
+        .to_string();
+    for (kind, code) in RecipeError::EXIT_CODES {
+        help.push_str(&format!("\n  {:<3} {}", code, kind));
+    }
+    help
+}
 
 #[derive(clap::Parser)]
+#[clap(after_long_help = exit_codes())]
+#[cfg_attr(feature = "serve", clap(subcommand_negates_reqs = true))]
 struct Opts {
+    /// Recipes to compute, a combined total is printed when there are several.
+    /// `-` reads a recipe from stdin. Relative paths of recipes, sub-recipes
+    /// and libraries that don't exist are also looked up in the directories
+    /// listed in `NUTRITIONS_PATH`.
+    #[clap(
+        long,
+        short,
+        required_unless_present_any = ["dir", "meal_plan", "print_schema"],
+        num_args = 1..
+    )]
+    recipe_file: Vec<PathBuf>,
+    /// Prints a summary of facts per 100g of every YAML and JSON recipe in
+    /// the directory instead.
+    #[clap(long, conflicts_with = "recipe_file")]
+    dir: Option<PathBuf>,
+    /// Summarizes only recipes of the directory tagged with this tag in their
+    /// metadata.
+    #[clap(long, requires = "dir")]
+    tag: Option<String>,
+    /// Summarizes recipes of the directory identical to an earlier one only
+    /// once, warning about the duplicates.
+    #[clap(long, requires = "dir")]
+    dedupe: bool,
+    /// Prints facts of every meal of the day in the given meal plan, and of
+    /// the whole day, instead.
+    #[clap(long, value_name = "FILE", conflicts_with_all = ["recipe_file", "dir"])]
+    meal_plan: Option<PathBuf>,
+    /// Prints the JSON Schema of recipe files instead, for editors to
+    /// complete and check them.
+    #[clap(long, conflicts_with_all = ["recipe_file", "dir"])]
+    print_schema: bool,
+    /// How many levels of nested sub-recipes may be loaded.
+    #[clap(long, default_value_t = DEFAULT_MAX_DEPTH)]
+    max_depth: usize,
+    /// Format the given recipes are written in, told by the file extension
+    /// when omitted and YAML for stdin. Sub-recipes and libraries are always
+    /// told by their extension.
+    #[clap(long, value_enum)]
+    format_in: Option<FormatIn>,
+    /// Output format of the computed facts.
+    #[clap(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Colors text output: energy in bold and nutrients above the maximum
+    /// of their constraints in red.
+    #[clap(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+    /// Decimal places of printed amounts.
+    #[clap(long, default_value_t = format::DEFAULT_PRECISION as u8)]
+    precision: u8,
+    /// Nutrients listed first in text and table output, in this order.
+    #[clap(long, value_delimiter = ',', value_parser = parse_nutrient)]
+    order: Vec<Nutrition>,
+    /// Amount of the dish the facts are given for.
+    #[clap(long, value_enum, default_value_t = Per::HundredGrams)]
+    per: Per,
+    /// Gives the facts for this many grams of the finished dish instead.
+    #[clap(long, value_name = "GRAMS", conflicts_with = "per")]
+    portion: Option<f64>,
+    /// Multiplies every ingredient amount before computing the facts.
+    #[clap(long)]
+    scale: Option<f64>,
+    /// Takes ingredient amounts as proportions and scales them so that the
+    /// raw ingredients weigh this many grams.
+    #[clap(long, conflicts_with = "scale")]
+    target_weight: Option<f64>,
+    /// Grams the finished dish weighs, overriding the weights in the file.
+    #[clap(long)]
+    weight: Option<f64>,
+    /// Estimates energy of products that declare only macronutrients.
+    #[clap(long)]
+    estimate_energy: bool,
+    /// Rounds the computed amounts of every nutrient.
+    #[clap(long, value_enum, default_value_t = Rounding::Exact)]
+    rounding: Rounding,
+    /// Fails for dishes that don't declare their weight instead of taking
+    /// the weight of their raw ingredients, and for ingredients weighing
+    /// more than twice the declared weight of their dish instead of warning.
+    #[clap(long)]
+    strict_weights: bool,
+    /// Fails for recipes with fields no recipe has, like misspelled ones,
+    /// instead of ignoring them.
+    #[clap(long)]
+    strict_parse: bool,
+    /// Reads amounts of products' facts written with a decimal comma, like
+    /// `10,5`.
+    #[clap(long)]
+    decimal_comma: bool,
+    /// Doesn't supply products recipes lack from the default product
+    /// database, `products.yaml` in `$NUTRITIONS_CONFIG_DIR` or in
+    /// `~/.config/nutritions`.
+    #[clap(long)]
+    no_default_db: bool,
+    /// Warns about ingredients whose product is missing and leaves them out,
+    /// their weight included, instead of failing.
+    #[clap(long)]
+    allow_missing: bool,
+    /// How errors are printed to stderr.
+    #[clap(long, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+    /// Computes facts of only the ingredients of these products, named like
+    /// in ingredients.
+    #[clap(
+        long,
+        value_name = "NAMES",
+        value_delimiter = ',',
+        conflicts_with = "exclude"
+    )]
+    only: Vec<String>,
+    /// Computes facts without the ingredients of these products.
+    #[clap(long, value_name = "NAMES", value_delimiter = ',')]
+    exclude: Vec<String>,
+    /// Prints amounts of every recipe next to the ones of this recipe instead
+    /// of the facts.
+    #[clap(long, value_name = "FILE")]
+    compare: Option<PathBuf>,
+    /// Also prints facts of every component of composed dishes.
+    #[clap(long)]
+    components: bool,
+    /// Prints the share of the daily intake of every nutrient, reading
+    /// reference intakes from the given YAML file or using 2000 kcal ones.
+    #[clap(long, value_name = "FILE", num_args = 0..=1)]
+    daily_values: Option<Option<PathBuf>>,
+    /// Checks the facts against the nutrient limits in the given YAML file,
+    /// failing when any is violated.
+    #[clap(long, value_name = "FILE")]
+    constraints: Option<PathBuf>,
+    /// Prints the price of the ingredients, in total and per serving.
+    #[clap(long)]
+    cost: bool,
+    /// Prints how much the raw ingredients and the finished dish weigh.
+    #[clap(long)]
+    weights: bool,
+    /// Prints what every ingredient contributes to the whole recipe.
+    #[clap(long)]
+    breakdown: bool,
+    /// Prints the lowest and highest amounts that ranges in product facts
+    /// allow in text output, instead of the amounts halfway between.
+    #[clap(long, conflicts_with = "daily_values")]
+    ranges: bool,
+    /// Prints which share of energy comes from each macronutrient.
+    #[clap(long)]
+    macro_ratios: bool,
+    /// Prints how the facts are computed before them, in the text and table
+    /// formats: what every ingredient adds and how the sum is normalized.
+    #[clap(long)]
+    explain: bool,
+    /// Prints amounts of macronutrients per 100 kcal instead of the facts,
+    /// in the text format.
+    #[clap(long, conflicts_with_all = ["ranges", "daily_values"])]
+    per_energy: bool,
+    /// Follows amounts weighed in grams with their share of the weight the
+    /// facts are given for, in the text format.
+    #[clap(long, conflicts_with_all = ["ranges", "daily_values", "per_energy"])]
+    weight_shares: bool,
+    /// Prints to stderr how long reading, parsing and aggregating recipes
+    /// took.
+    #[clap(long)]
+    timings: bool,
+    /// Logs every step of the calculation.
     #[clap(long, short)]
-    recipe_file: PathBuf,
+    verbose: bool,
+    /// Prints the facts again whenever a recipe file, one of its libraries or
+    /// sub-recipes changes, until interrupted.
+    #[clap(long, conflicts_with_all = ["dir", "meal_plan", "print_schema"])]
+    watch: bool,
+    #[cfg(feature = "serve")]
+    #[clap(subcommand)]
+    command: Option<Command>,
+    /// Collects the time spent when `timings` is set.
+    #[clap(skip)]
+    recorded_timings: Arc<Timings>,
 }
 
-async fn cli() -> Result<(), Box<dyn Error>> {
-    let opts = Opts::parse();
-    let recipe = Recipe::read_from_file(&opts.recipe_file).await?;
-    let facts = recipe.get_nutrition_facts().await?;
+/// Computed facts of a recipe as they are printed.
+struct Report {
+    name: String,
+    facts: NutritionFacts,
+    /// Lowest and highest facts, when ranges are asked for.
+    range: Option<NutritionRange>,
+    /// Lines printed after the facts in text formats.
+    notes: Vec<String>,
+    /// Contribution of every ingredient, when asked for.
+    breakdown: Vec<(String, NutritionFacts)>,
+    /// Steps of computing the facts, when asked for.
+    explanation: Option<Explanation>,
+    /// Grams the facts are given for, the serving size on a label.
+    serving_weight: f64,
+}
+
+/// Serializes reports as a map of their names to their facts, in order.
+struct Named<'a>(&'a [Report]);
+
+impl Serialize for Named<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|report| (&report.name, &report.facts)))
+    }
+}
+
+/// Facts per 100g of every `*.yaml`, `*.yml` and `*.json` recipe in `dir`
+/// named by the file, logging the recipes that fail and, when deduplicating,
+/// those identical to an earlier one.
+async fn summarize(
+    dir: &Path,
+    opts: &Opts,
+) -> Result<Vec<(String, NutritionFacts)>, Box<dyn Error>> {
+    let mut paths = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let is_recipe = path
+            .extension()
+            .is_some_and(|extension| ["yaml", "yml", "json"].iter().any(|e| extension == *e));
+        if is_recipe && entry.file_type().await?.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    let options = compute_options(opts);
+    let results = join_all(paths.iter().map(|path| async {
+        let recipe = Recipe::read_from_file_with(
+            path,
+            InputFormat::from_path(path),
+            &opts.recorded_timings,
+            parse_options(opts),
+        )
+        .await?;
+        if opts
+            .tag
+            .as_ref()
+            .is_some_and(|tag| !recipe.metadata().has_tag(tag))
+        {
+            return Ok(None);
+        }
+        let facts = recipe.get_nutrition_facts_with(&options).await?;
+        Ok::<_, RecipeError>(Some((recipe, facts)))
+    }))
+    .await;
+    let mut reports = Vec::new();
+    let mut seen: HashMap<Recipe, String> = HashMap::new();
+    for (path, result) in paths.iter().zip(results) {
+        let name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .into_owned();
+        match result {
+            Ok(Some((recipe, facts))) => {
+                if opts.dedupe {
+                    if let Some(first) = seen.get(&recipe) {
+                        log::warn!("Skipped {}, the same recipe as {}", name, first);
+                        continue;
+                    }
+                    seen.insert(recipe, name.clone());
+                }
+                reports.push((name, facts))
+            }
+            Ok(None) => {}
+            Err(err) => log::error!("Skipped {}: {}", name, err),
+        }
+    }
+    Ok(reports)
+}
+
+fn parse_nutrient(name: &str) -> Result<Nutrition, String> {
+    Ok(Nutrition::from_name(name.trim()))
+}
+
+fn compute_options(opts: &Opts) -> ComputeOptions {
+    ComputeOptions {
+        max_depth: opts.max_depth,
+        estimate_energy: opts.estimate_energy,
+        allow_missing: opts.allow_missing,
+        timings: opts.timings.then(|| opts.recorded_timings.clone()),
+        strict_weights: opts.strict_weights,
+        rounding: opts.rounding.into(),
+        bound: None,
+        parse: parse_options(opts),
+        ingredients: if !opts.only.is_empty() {
+            IngredientFilter::Only(opts.only.clone())
+        } else if !opts.exclude.is_empty() {
+            IngredientFilter::Exclude(opts.exclude.clone())
+        } else {
+            IngredientFilter::All
+        },
+    }
+}
+
+fn parse_options(opts: &Opts) -> ParseOptions {
+    ParseOptions {
+        strict: opts.strict_parse,
+        decimal_comma: opts.decimal_comma,
+        default_database: !opts.no_default_db,
+        no_files: false,
+    }
+}
+
+async fn read_recipe(path: &Path, opts: &Opts) -> Result<Recipe, Box<dyn Error>> {
+    let format = opts.format_in;
+    if path != Path::new("-") {
+        let format = format.map_or_else(|| InputFormat::from_path(path), InputFormat::from);
+        let timings = &opts.recorded_timings;
+        return Ok(Recipe::read_from_file_with(path, format, timings, parse_options(opts)).await?);
+    }
+    let mut contents = String::new();
+    tokio::io::stdin().read_to_string(&mut contents).await?;
+    if contents.trim().is_empty() {
+        return Err("No recipe given on stdin".into());
+    }
+    let format = format.map_or(InputFormat::Yaml, InputFormat::from);
+    let recipe = Recipe::from_str_with(&contents, format, parse_options(opts))?;
+    Ok(recipe.resolve_includes_with(parse_options(opts)).await?)
+}
+
+fn warn_energy_discrepancy(subject: &str, discrepancy: f64) {
+    log::warn!(
+        "Declared energy of {} differs from macronutrients by {:.0}%",
+        subject,
+        discrepancy * 100.0
+    );
+}
+
+/// Report of the recipe at `path` named by the path, followed by reports of
+/// its components when they are asked for.
+async fn compute(path: &Path, opts: &Opts) -> Result<Vec<Report>, Box<dyn Error>> {
+    let mut recipe = read_recipe(path, opts).await?;
+    if opts.verbose {
+        let unused = recipe.unused_products();
+        if !unused.is_empty() {
+            log::warn!(
+                "Unused products in {}: {}",
+                path.to_string_lossy(),
+                unused.join(", ")
+            );
+        }
+        for (product, grams) in recipe.overweight_products() {
+            log::warn!("Nutrients of {} add up to {:.1}g per 100g", product, grams);
+        }
+    }
+    for (product, discrepancy) in recipe.energy_discrepancies() {
+        warn_energy_discrepancy(product, discrepancy);
+    }
+    if !opts.strict_weights {
+        for (ingredient, grams, weight) in recipe.overweight_ingredients() {
+            log::warn!(
+                "Ingredient {} of {} weighs {:.0}g, more than the whole dish of {:.0}g",
+                ingredient,
+                path.to_string_lossy(),
+                grams,
+                weight
+            );
+        }
+    }
+    if let Some(factor) = opts.scale {
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(format!("Invalid scale factor: {}", factor).into());
+        }
+        recipe = recipe.scaled(factor);
+    }
+    if let Some(weight) = opts.target_weight {
+        recipe = recipe.scaled_to(weight)?;
+    }
+    if let Some(weight) = opts.weight {
+        recipe = recipe.with_weight(weight)?;
+    }
+    let name = path.to_string_lossy();
+    let report = compute_recipe(name.to_string(), &recipe, opts).await?;
+    if let Some(discrepancy) = report.facts.energy_discrepancy() {
+        if discrepancy > ENERGY_DISCREPANCY_THRESHOLD {
+            warn_energy_discrepancy(&name, discrepancy);
+        }
+    }
+    let mut reports = vec![report];
+    if opts.components {
+        for (component, recipe) in recipe.components() {
+            let name = format!("{} / {}", name, component);
+            reports.push(compute_recipe(name, &recipe, opts).await?);
+        }
+    }
+    Ok(reports)
+}
+
+async fn compute_recipe(
+    name: String,
+    recipe: &Recipe,
+    opts: &Opts,
+) -> Result<Report, Box<dyn Error>> {
+    let options = compute_options(opts);
+    let serving_weight = match (opts.format, recipe.serving_weight()) {
+        (Format::Label, Ok(weight)) => Some(weight),
+        (Format::Label, Err(RecipeError::MissingServings)) => None,
+        (Format::Label, Err(err)) => return Err(err.into()),
+        _ => None,
+    };
+    // Labels are of a serving whenever the recipe has servings.
+    let per = match (opts.format, serving_weight) {
+        (Format::Label, Some(_)) => Per::Serving,
+        (Format::Label, None) => Per::HundredGrams,
+        _ => opts.per,
+    };
+    let facts = facts_per(recipe, per, opts.portion, &options).await?;
+    let range = if opts.ranges {
+        let at = |bound| ComputeOptions {
+            bound: Some(bound),
+            ..options.clone()
+        };
+        Some(NutritionRange {
+            min: facts_per(recipe, per, opts.portion, &at(Bound::Min)).await?,
+            max: facts_per(recipe, per, opts.portion, &at(Bound::Max)).await?,
+        })
+    } else {
+        None
+    };
+    let mut notes = Vec::new();
+    let allergens = recipe.allergens_with(&options).await?;
+    if !allergens.is_empty() {
+        notes.push(format!("Allergens: {}", allergens.join(", ")));
+    }
+    if opts.weights {
+        let computed = recipe.compute_with(&options).await?;
+        notes.push(format!(
+            "Raw weight: {:.0}g, Dish weight: {:.0}g",
+            computed.raw_weight, computed.dish_weight
+        ));
+    }
+    if opts.cost {
+        let cost = recipe.total_cost_with(&options)?;
+        notes.push(format!("Cost: {:.2}", cost.total));
+        if let Some(per_serving) = cost.per_serving {
+            notes.push(format!("Cost per serving: {:.2}", per_serving));
+        }
+    }
+    let explanation = if opts.explain {
+        Some(recipe.explain_with(&options).await?)
+    } else {
+        None
+    };
+    let breakdown = if opts.breakdown {
+        recipe.breakdown_with(&options).await?
+    } else {
+        Vec::new()
+    };
+    Ok(Report {
+        name,
+        facts,
+        range,
+        notes,
+        breakdown,
+        explanation,
+        serving_weight: match (opts.portion.or(serving_weight), per) {
+            (Some(grams), _) => grams,
+            (None, Per::HundredGrams) => 100.0,
+            (None, Per::Serving) => recipe.serving_weight()?,
+            (None, Per::Total) => recipe.compute_with(&options).await?.dish_weight,
+        },
+    })
+}
+
+/// Facts for `per`, or for a portion of the given grams when there's one.
+async fn facts_per(
+    recipe: &Recipe,
+    per: Per,
+    portion: Option<f64>,
+    options: &ComputeOptions,
+) -> Result<NutritionFacts, RecipeError> {
+    if let Some(grams) = portion {
+        return recipe
+            .get_nutrition_facts_for_portion_with(grams, options)
+            .await;
+    }
+    match per {
+        Per::HundredGrams => recipe.get_nutrition_facts_with(options).await,
+        Per::Serving => recipe.get_nutrition_facts_per_serving_with(options).await,
+        Per::Total => recipe.get_total_nutrition_with(options).await,
+    }
+}
+
+async fn cli(opts: &Opts) -> Result<(), Box<dyn Error>> {
+    env_logger::Builder::new()
+        .filter_level(if opts.verbose {
+            log::LevelFilter::Debug
+        } else {
+            log::LevelFilter::Warn
+        })
+        .parse_default_env()
+        .init();
+    if opts.watch {
+        return watch(opts).await;
+    }
+    let start = Instant::now();
+    let result = run(opts).await;
+    if opts.timings {
+        eprintln!("{}, total: {:.3?}", opts.recorded_timings, start.elapsed());
+    }
+    result
+}
+
+/// Runs again whenever one of the files read changes, printing errors
+/// instead of stopping at them.
+async fn watch(opts: &Opts) -> Result<(), Box<dyn Error>> {
+    if opts.recipe_file.iter().any(|path| path == Path::new("-")) {
+        return Err("Recipes read from stdin can't be watched".into());
+    }
+    let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            // The receiver only goes away along with the watcher.
+            let _ = sender.send(event);
+        }
+    })?;
+    // Directories are watched rather than files, as editors often replace
+    // files instead of writing to them.
+    let mut watched = BTreeSet::new();
+    let mut files = BTreeSet::new();
+    loop {
+        print!("\x1b[2J\x1b[H");
+        if let Err(err) = run(opts).await {
+            eprintln!("{}", err);
+        }
+        files = watched_files(opts, &files).await;
+        for dir in files.iter().filter_map(|file| file.parent()) {
+            if dir.is_dir() && watched.insert(dir.to_path_buf()) {
+                watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            }
+        }
+        loop {
+            let Some(event) = events.recv().await else {
+                return Ok(());
+            };
+            let changed =
+                event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove();
+            if changed && event.paths.iter().any(|path| files.contains(path)) {
+                break;
+            }
+        }
+        // Saving often takes several events, let them all arrive.
+        tokio::time::sleep(WATCH_DEBOUNCE).await;
+        while events.try_recv().is_ok() {}
+    }
+}
+
+/// Files the recipes given are read from. For recipes that fail to load
+/// the recipe files are watched along with the `previous` files, those
+/// watched before they broke.
+async fn watched_files(opts: &Opts, previous: &BTreeSet<PathBuf>) -> BTreeSet<PathBuf> {
+    let mut files = BTreeSet::new();
+    for path in &opts.recipe_file {
+        let read = match read_recipe(path, opts).await {
+            Ok(recipe) => recipe.files_with(parse_options(opts)).await.ok(),
+            Err(_) => None,
+        };
+        match read {
+            Some(read) => files.extend(read),
+            None => {
+                files.extend(std::fs::canonicalize(path).ok());
+                files.extend(previous.iter().cloned());
+            }
+        }
+    }
+    files
+}
+
+/// Computes and prints what `opts` ask for.
+async fn run(opts: &Opts) -> Result<(), Box<dyn Error>> {
+    let style = Style {
+        precision: opts.precision.into(),
+        order: if opts.order.is_empty() {
+            Style::default().order
+        } else {
+            opts.order.clone()
+        },
+    };
+    #[cfg(feature = "serve")]
+    if let Some(Command::Serve { addr }) = &opts.command {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        eprintln!("Listening on {}", listener.local_addr()?);
+        nutritions::serve::serve(listener, compute_options(opts)).await?;
+        return Ok(());
+    }
+    if opts.print_schema {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&nutritions::recipe_schema())?
+        );
+        return Ok(());
+    }
+    if let Some(dir) = &opts.dir {
+        print!("{}", format::summary(&summarize(dir, opts).await?, &style));
+        return Ok(());
+    }
+    if (opts.per_energy || opts.weight_shares) && !matches!(opts.format, Format::Text) {
+        return Err("Amounts per energy and shares of weight are printed as text only".into());
+    }
+    if opts.explain && !matches!(opts.format, Format::Text | Format::Table) {
+        return Err("Explanations are printed as text and tables only".into());
+    }
+    let reports = match &opts.meal_plan {
+        Some(path) => meal_plan_reports(path, opts).await?,
+        None => recipe_reports(opts).await?,
+    };
+
+    let mut violations = 0;
+    let constraints = match &opts.constraints {
+        Some(path) => Some(Constraints::read_from_file(path).await?),
+        None => None,
+    };
+    if let Some(constraints) = &constraints {
+        for report in &reports {
+            for violation in report.facts.check(constraints) {
+                eprintln!("{}: {}", report.name, violation);
+                violations += 1;
+            }
+        }
+    }
+
+    if let Some(compared) = &opts.compare {
+        let recipe = read_recipe(compared, opts).await?;
+        let other = compute_recipe(compared.to_string_lossy().into_owned(), &recipe, opts).await?;
+        for report in &reports {
+            println!(
+                "Compare: {} vs {}\n{}",
+                report.name,
+                other.name,
+                format::comparison(&report.facts.compare(&other.facts), &style)
+            );
+        }
+        return check_violations(violations);
+    }
+    match (opts.format, reports.as_slice()) {
+        (Format::Json, [report]) => println!("{}", serde_json::to_string_pretty(&report.facts)?),
+        (Format::Json, _) => println!("{}", serde_json::to_string_pretty(&Named(&reports))?),
+        (Format::Yaml, [report]) => print!("{}", serde_yaml::to_string(&report.facts)?),
+        (Format::Yaml, _) => print!("{}", serde_yaml::to_string(&Named(&reports))?),
+        (Format::Csv, [report]) => print!("{}", format::csv(&report.facts, &style)),
+        (Format::Csv, _) => {
+            let reports: Vec<(String, NutritionFacts)> = reports
+                .into_iter()
+                .map(|report| (report.name, report.facts))
+                .collect();
+            print!("{}", format::csv_named(&reports, &style))
+        }
+        (Format::Label, _) => {
+            for report in &reports {
+                println!(
+                    "Facts: {}\n{}",
+                    report.name,
+                    format::label(&report.facts, report.serving_weight, &style)
+                );
+                print_notes(&report.notes);
+            }
+        }
+        (Format::Table, _) => {
+            for report in &reports {
+                print_explanation(report.explanation.as_ref(), &style);
+                println!(
+                    "Facts: {}\n{}",
+                    report.name,
+                    format::table(&report.facts, &style)
+                );
+                print_notes(&report.notes);
+                print_breakdown(&report.breakdown, &style);
+            }
+        }
+        (Format::Text, _) => {
+            let daily_values = match &opts.daily_values {
+                Some(Some(path)) => Some(DailyValues::read_from_file(path).await?),
+                Some(None) => Some(DailyValues::default()),
+                None => None,
+            };
+            let color = opts.color.enabled();
+            for Report {
+                name,
+                facts,
+                range,
+                notes,
+                breakdown,
+                explanation,
+                serving_weight,
+            } in &reports
+            {
+                let text = if opts.weight_shares {
+                    format::weight_shares(facts, *serving_weight, &style)
+                } else {
+                    format::text(facts, &style)
+                };
+                print_explanation(explanation.as_ref(), &style);
+                match (range, &daily_values) {
+                    _ if opts.per_energy => match facts.per_energy() {
+                        Some(per_energy) => println!(
+                            "Facts per 100 kcal: {}\n{}",
+                            name,
+                            format::per_energy(&per_energy, &style)
+                        ),
+                        None => println!(
+                            "Facts per 100 kcal: {}\nNo energy to relate amounts to\n",
+                            name
+                        ),
+                    },
+                    (Some(range), _) => {
+                        println!("Facts: {}\n{}", name, format::range(range, &style))
+                    }
+                    (None, Some(dv)) => println!(
+                        "Facts: {}\n{}",
+                        name,
+                        format::daily_values(facts, dv, &style)
+                    ),
+                    (None, None) if color => {
+                        let exceeded = exceeded_nutrients(facts, constraints.as_ref());
+                        println!(
+                            "{}\n{}",
+                            format!("Facts: {}", name).bold(),
+                            colorize(&text, &exceeded)
+                        )
+                    }
+                    (None, None) => println!("Facts: {}\n{}", name, text),
+                }
+                if opts.macro_ratios {
+                    println!("{}\n", facts.macro_ratios());
+                }
+                print_notes(notes);
+                print_breakdown(breakdown, &style);
+            }
+        }
+    }
+    check_violations(violations)
+}
 
-    println!("Facts: {}\n{}", opts.recipe_file.to_string_lossy(), facts);
-    Ok(())
+/// Nutrients of `facts` above the maximum `constraints` allow.
+fn exceeded_nutrients(facts: &NutritionFacts, constraints: Option<&Constraints>) -> Vec<Nutrition> {
+    constraints
+        .map(|constraints| facts.check(constraints))
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|violation| match violation {
+            Violation::Above { nutrient, .. } => Some(nutrient),
+            Violation::Below { .. } => None,
+        })
+        .collect()
+}
+
+/// Colors lines of text facts: energy in bold and `exceeded` nutrients in
+/// red.
+fn colorize(text: &str, exceeded: &[Nutrition]) -> String {
+    let mut out = String::new();
+    for line in text.lines() {
+        let nutrient = Nutrition::from_name(line.split_once(":  ").map_or(line, |(name, _)| name));
+        if exceeded.contains(&nutrient) {
+            out.push_str(&line.red().to_string());
+        } else if nutrient == Nutrition::Energy {
+            out.push_str(&line.bold().to_string());
+        } else {
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Reports of every recipe given, followed by their total when there are
+/// several.
+async fn recipe_reports(opts: &Opts) -> Result<Vec<Report>, Box<dyn Error>> {
+    let all_reports = try_join_all(opts.recipe_file.iter().map(|path| compute(path, opts))).await?;
+
+    let serving_weight = all_reports
+        .iter()
+        .filter_map(|reports| reports.first())
+        .map(|report| report.serving_weight)
+        .sum();
+    let total = all_reports
+        .iter()
+        .filter_map(|reports| reports.first())
+        .map(|report| report.facts.clone())
+        .fold(NutritionFacts::default(), |total, facts| total + facts);
+    let total_range = all_reports
+        .iter()
+        .filter_map(|reports| reports.first())
+        .map(|report| report.range.clone())
+        .collect::<Option<Vec<_>>>()
+        .map(|ranges| {
+            ranges
+                .into_iter()
+                .fold(NutritionRange::default(), |total, range| total + range)
+        });
+    let recipes = all_reports.len();
+    let mut reports: Vec<Report> = all_reports.into_iter().flatten().collect();
+    if recipes > 1 {
+        reports.push(Report {
+            name: "Total".into(),
+            facts: total,
+            range: total_range,
+            notes: Vec::new(),
+            breakdown: Vec::new(),
+            explanation: None,
+            serving_weight,
+        });
+    }
+    Ok(reports)
+}
+
+/// Reports of the portion of every meal of the plan at `path`, followed by
+/// the daily total.
+async fn meal_plan_reports(path: &Path, opts: &Opts) -> Result<Vec<Report>, Box<dyn Error>> {
+    let facts = MealPlan::read_from_file(path)
+        .await?
+        .compute_with(&compute_options(opts))
+        .await?;
+    let report = |name, facts, serving_weight| Report {
+        name,
+        facts,
+        range: None,
+        notes: Vec::new(),
+        breakdown: Vec::new(),
+        explanation: None,
+        serving_weight,
+    };
+    let day_weight = facts.meals.iter().map(|meal| meal.portion_g).sum();
+    let mut reports: Vec<Report> = facts
+        .meals
+        .into_iter()
+        .map(|meal| report(meal.name, meal.facts, meal.portion_g))
+        .collect();
+    reports.push(report("Total".into(), facts.total, day_weight));
+    Ok(reports)
+}
+
+fn check_violations(violations: usize) -> Result<(), Box<dyn Error>> {
+    match violations {
+        0 => Ok(()),
+        1 => Err("1 constraint is violated".into()),
+        _ => Err(format!("{} constraints are violated", violations).into()),
+    }
+}
+
+fn print_notes(notes: &[String]) {
+    if !notes.is_empty() {
+        println!("{}\n", notes.join("\n"));
+    }
+}
+
+fn print_explanation(explanation: Option<&Explanation>, style: &Style) {
+    if let Some(explanation) = explanation {
+        println!("{}", format::explanation(explanation, style));
+    }
+}
+
+fn print_breakdown(breakdown: &[(String, NutritionFacts)], style: &Style) {
+    if !breakdown.is_empty() {
+        println!("{}", format::breakdown(breakdown, style));
+    }
+}
+
+/// Prints `err` to stderr as `format` asks, exiting with its code.
+fn fail(err: &(dyn Error + 'static), format: ErrorFormat) -> ! {
+    let recipe_error = err.downcast_ref::<RecipeError>();
+    let code = recipe_error.map_or(1, RecipeError::exit_code);
+    match format {
+        ErrorFormat::Text => eprintln!("{}", err),
+        ErrorFormat::Json => eprintln!(
+            "{}",
+            serde_json::json!({
+                "kind": recipe_error.map_or("other", RecipeError::kind),
+                "code": code,
+                "message": err.to_string(),
+            })
+        ),
+    }
+    std::process::exit(code)
 }
 
 #[tokio::main]
 async fn main() {
-    cli().await.unwrap();
+    let opts = Opts::parse();
+    if let Err(err) = cli(&opts).await {
+        fail(&*err, opts.error_format);
+    }
 }
@@ -0,0 +1,195 @@
+//! Daily totals of the recipes eaten through a day.
+
+use std::path::{Path, PathBuf};
+
+use futures::future::try_join_all;
+use serde::Deserialize;
+
+use crate::{
+    error::RecipeError,
+    recipe::{check, locate, ComputeOptions, InputFormat, NutritionFacts, Recipe},
+};
+
+/// Meals of a day, each a portion of a recipe.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MealPlan {
+    meals: Vec<Meal>,
+    /// Directory of the file the plan was read from, where its recipes are
+    /// looked up first.
+    #[serde(skip)]
+    dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Meal {
+    /// Named after the file stem of the recipe when omitted.
+    name: Option<String>,
+    recipe: PathBuf,
+    /// Grams of the finished recipe eaten.
+    portion_g: f64,
+}
+
+impl Meal {
+    fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            self.recipe.file_stem().map_or_else(
+                || self.recipe.to_string_lossy().into_owned(),
+                |stem| stem.to_string_lossy().into_owned(),
+            )
+        })
+    }
+}
+
+/// Facts of the portion of a meal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MealFacts {
+    pub name: String,
+    pub portion_g: f64,
+    pub facts: NutritionFacts,
+}
+
+/// Facts of every meal of a plan, in order, and of the whole day.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MealPlanFacts {
+    pub meals: Vec<MealFacts>,
+    pub total: NutritionFacts,
+}
+
+impl MealPlan {
+    /// Reads a plan from a YAML file listing `meals`, each with the `recipe`
+    /// file eaten and the `portion_g` of it. Recipe paths are relative to
+    /// the plan, as sub-recipe paths are to their recipe.
+    pub async fn read_from_file(path: &Path) -> Result<Self, RecipeError> {
+        let yaml = tokio::fs::read_to_string(path).await?;
+        let mut plan = Self::from_yaml_str(&yaml).map_err(|err| err.in_file(path))?;
+        plan.dir = path.parent().map(Path::to_path_buf);
+        Ok(plan)
+    }
+
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, RecipeError> {
+        let plan: MealPlan = serde_yaml::from_str(yaml)?;
+        for meal in &plan.meals {
+            check(
+                || format!("meals[{}].portion_g", meal.name()),
+                meal.portion_g,
+                |grams| grams > 0.0,
+            )?;
+        }
+        Ok(plan)
+    }
+
+    pub async fn compute(&self) -> Result<MealPlanFacts, RecipeError> {
+        self.compute_with(&ComputeOptions::default()).await
+    }
+
+    /// Facts of the portion of every meal, the recipes being read and
+    /// computed with `options`, and their sum.
+    pub async fn compute_with(
+        &self,
+        options: &ComputeOptions,
+    ) -> Result<MealPlanFacts, RecipeError> {
+        let meals = try_join_all(self.meals.iter().map(|meal| async move {
+            let path = locate(&meal.recipe, self.dir.as_deref()).await?;
+            let facts = async {
+                let recipe = Recipe::load(
+                    &path,
+                    InputFormat::from_path(&path),
+                    options.timings.as_deref(),
+                    options.parse,
+                )
+                .await?;
+                recipe.get_nutrition_facts_with(options).await
+            }
+            .await
+            .map_err(|err| match err {
+                RecipeError::InRecipe { .. } => err,
+                err => RecipeError::InRecipe {
+                    chain: vec![path],
+                    source: Box::new(err),
+                },
+            })?;
+            Ok::<_, RecipeError>(MealFacts {
+                name: meal.name(),
+                portion_g: meal.portion_g,
+                facts: facts * (meal.portion_g / 100.0),
+            })
+        }))
+        .await?;
+        let total = meals
+            .iter()
+            .map(|meal| meal.facts.clone())
+            .fold(NutritionFacts::default(), |total, facts| total + facts);
+        Ok(MealPlanFacts { meals, total })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::MealPlan;
+    use crate::{error::RecipeError, ComputeOptions, Nutrition, ParseOptions};
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn calculate_daily_totals() {
+        let plan = MealPlan::read_from_file(Path::new("tests/fixtures/meal-plan/day.yaml"))
+            .await
+            .unwrap();
+
+        let facts = plan.compute().await.unwrap();
+
+        let names: Vec<&str> = facts.meals.iter().map(|meal| meal.name.as_str()).collect();
+        assert_eq!(names, ["Breakfast", "Lunch", "salad"]);
+        assert_close(facts.meals[0].facts.get(Nutrition::Energy), 305.0);
+        assert_close(facts.meals[1].facts.get(Nutrition::Energy), 450.0);
+        assert_close(facts.meals[2].facts.get(Nutrition::Energy), 18.0);
+        assert_close(facts.total.get(Nutrition::Energy), 773.0);
+        assert_close(facts.total.get(Nutrition::Proteins), 64.5);
+    }
+
+    #[tokio::test]
+    async fn read_meals_as_options_tell() {
+        let plan = MealPlan::read_from_file(Path::new("tests/fixtures/meal-plan/misspelled.yaml"))
+            .await
+            .unwrap();
+        let strict = ComputeOptions {
+            parse: ParseOptions {
+                strict: true,
+                ..ParseOptions::default()
+            },
+            ..ComputeOptions::default()
+        };
+
+        let lenient = plan.compute().await.unwrap();
+        let err = plan.compute_with(&strict).await.err().unwrap();
+
+        assert_close(lenient.total.get(Nutrition::Energy), 900.0);
+        match err {
+            RecipeError::InRecipe { source, .. } => {
+                assert!(matches!(*source, RecipeError::UnknownFields { .. }))
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn fail_invalid_portion() {
+        match MealPlan::from_yaml_str("meals:\n  - recipe: soup.yaml\n    portion_g: 0\n") {
+            Err(RecipeError::InvalidValue { field, .. }) => {
+                assert_eq!(field, "meals[soup].portion_g")
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("empty portion should be rejected"),
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Nutrient amounts known only within bounds, as sources giving ranges like
+//! 10–12g of fat have them.
+
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    fmt,
+    ops::{Add, Mul},
+};
+
+use schemars::JsonSchema;
+use serde::{
+    de::{self, Unexpected},
+    Deserialize, Deserializer,
+};
+
+use crate::{
+    error::RecipeError,
+    format::{self, Style, DEFAULT_PRECISION},
+    recipe::{ComputeOptions, Nutrition, NutritionFacts, Recipe},
+};
+
+/// Which bound of ranged amounts facts are computed with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Min,
+    Max,
+}
+
+/// Lowest and highest facts, holding the same nutrients.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NutritionRange {
+    pub min: NutritionFacts,
+    pub max: NutritionFacts,
+}
+
+impl NutritionRange {
+    /// Facts at `bound`, halfway between the bounds when not given.
+    pub fn at(&self, bound: Option<Bound>) -> NutritionFacts {
+        match bound {
+            Some(Bound::Min) => self.min.clone(),
+            Some(Bound::Max) => self.max.clone(),
+            None => (self.min.clone() + self.max.clone()) * 0.5,
+        }
+    }
+}
+
+impl Add for NutritionRange {
+    type Output = NutritionRange;
+
+    fn add(self, other: NutritionRange) -> NutritionRange {
+        NutritionRange {
+            min: self.min + other.min,
+            max: self.max + other.max,
+        }
+    }
+}
+
+impl Mul<f64> for NutritionRange {
+    type Output = NutritionRange;
+
+    fn mul(self, factor: f64) -> NutritionRange {
+        NutritionRange {
+            min: self.min * factor,
+            max: self.max * factor,
+        }
+    }
+}
+
+/// Amounts are written as `min–max`, or as one amount when both bounds
+/// round to it.
+impl fmt::Display for NutritionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let style = Style {
+            precision: f.precision().unwrap_or(DEFAULT_PRECISION),
+            ..Style::default()
+        };
+        f.write_str(&format::range(self, &style))
+    }
+}
+
+/// Amount of a nutrient as written in a product's facts, either exact or a
+/// `{min, max}` range.
+#[derive(Clone, Copy, JsonSchema)]
+#[serde(untagged)]
+pub(crate) enum Amount {
+    Exact(f64),
+    Range { min: f64, max: f64 },
+}
+
+thread_local! {
+    /// Whether amounts being deserialized on this thread may be written with
+    /// a decimal comma.
+    static DECIMAL_COMMA: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `parse`, amounts it deserializes being read with a decimal comma
+/// when `decimal_comma` is set. Amounts are deserialized straight from the
+/// recipe text, so that errors keep their location in it.
+pub(crate) fn with_decimal_comma<T>(decimal_comma: bool, parse: impl FnOnce() -> T) -> T {
+    let previous = DECIMAL_COMMA.with(|enabled| enabled.replace(decimal_comma));
+    let parsed = parse();
+    DECIMAL_COMMA.with(|enabled| enabled.set(previous));
+    parsed
+}
+
+/// Number or the string an amount with a decimal comma is read as.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Number {
+    Number(f64),
+    Text(String),
+}
+
+impl Number {
+    fn value<E: de::Error>(self) -> Result<f64, E> {
+        match self {
+            Number::Number(value) => Ok(value),
+            Number::Text(text) => {
+                let number = DECIMAL_COMMA
+                    .with(Cell::get)
+                    .then(|| text.trim())
+                    .filter(|text| text.matches(',').count() == 1)
+                    .and_then(|text| text.replace(',', ".").parse().ok());
+                number.ok_or_else(|| E::invalid_type(Unexpected::Str(&text), &"a number"))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WrittenAmount {
+    Exact(Number),
+    Range { min: Number, max: Number },
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match WrittenAmount::deserialize(deserializer)? {
+            WrittenAmount::Exact(value) => Amount::Exact(value.value()?),
+            WrittenAmount::Range { min, max } => Amount::Range {
+                min: min.value()?,
+                max: max.value()?,
+            },
+        })
+    }
+}
+
+impl Amount {
+    pub(crate) fn is_exact(&self) -> bool {
+        matches!(self, Amount::Exact(_))
+    }
+}
+
+impl NutritionRange {
+    /// Range of written amounts, exact ones being both of their bounds.
+    pub(crate) fn from_amounts(amounts: &BTreeMap<Nutrition, Amount>) -> Self {
+        let bound = |pick: fn(f64, f64) -> f64| {
+            NutritionFacts(
+                amounts
+                    .iter()
+                    .map(|(nutrient, amount)| {
+                        let value = match *amount {
+                            Amount::Exact(value) => value,
+                            Amount::Range { min, max } => pick(min, max),
+                        };
+                        (nutrient.clone(), value)
+                    })
+                    .collect(),
+            )
+        };
+        Self {
+            min: bound(|min, _| min),
+            max: bound(|_, max| max),
+        }
+    }
+}
+
+impl Recipe {
+    /// Facts per 100g computed with the lower and with the upper bound of
+    /// every ranged amount. Exact amounts are both bounds of themselves.
+    pub async fn get_nutrition_range(&self) -> Result<NutritionRange, RecipeError> {
+        self.get_nutrition_range_with(&ComputeOptions::default())
+            .await
+    }
+
+    pub async fn get_nutrition_range_with(
+        &self,
+        options: &ComputeOptions,
+    ) -> Result<NutritionRange, RecipeError> {
+        let at = |bound| ComputeOptions {
+            bound: Some(bound),
+            ..options.clone()
+        };
+        Ok(NutritionRange {
+            min: self.get_nutrition_facts_with(&at(Bound::Min)).await?,
+            max: self.get_nutrition_facts_with(&at(Bound::Max)).await?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Nutrition, Recipe};
+
+    #[tokio::test]
+    async fn calculate_range() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Cheese
+    facts:
+      Energy: 350
+      Fats:
+        min: 20
+        max: 30
+  - name: Bread
+    facts:
+      Energy: 250
+      Fats: 2
+dish:
+  ingredients:
+    - product: Cheese
+      amount: 50
+    - product: Bread
+      amount: 50
+"#,
+        )
+        .unwrap();
+
+        let range = recipe.get_nutrition_range().await.unwrap();
+        let nominal = recipe.get_nutrition_facts().await.unwrap();
+
+        assert_eq!(range.min.get(Nutrition::Fats), Some(11.0));
+        assert_eq!(range.max.get(Nutrition::Fats), Some(16.0));
+        assert_eq!(range.min.get(Nutrition::Energy), Some(300.0));
+        assert_eq!(range.max.get(Nutrition::Energy), Some(300.0));
+        assert_eq!(nominal.get(Nutrition::Fats), Some(13.5));
+        assert_eq!(
+            format!("{:.0}", range),
+            "Energy:  300 kcal\nFats:  11–16 g\n"
+        );
+    }
+}
@@ -1,208 +1,5576 @@
 use async_recursion::async_recursion;
 use core::fmt;
-use enum_iterator::Sequence;
-use serde::Deserialize;
+use futures::future::try_join_all;
+use log::{debug, warn};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
 use std::{
-    collections::HashMap,
-    error::Error,
-    fmt::Write,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ffi::{OsStr, OsString},
+    hash::{Hash, Hasher},
+    io::{self, Read},
+    ops::{Add, Mul, Sub},
     path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    time::Instant,
 };
-use tokio::io::AsyncReadExt;
 
-#[derive(Clone, Default, Debug, PartialEq, Deserialize)]
-pub struct NutritionFacts(HashMap<Nutrition, f64>);
+use crate::{
+    accumulator::NutritionAccumulator,
+    energy::ENERGY_DISCREPANCY_THRESHOLD,
+    error::RecipeError,
+    format::{self, Style, DEFAULT_PRECISION},
+    range::{self, Amount, Bound, NutritionRange},
+    schema,
+    timings::Timings,
+    unit::Unit,
+};
+
+/// Decimals of amounts in [`NutritionFacts::to_canonical_string`].
+pub const CANONICAL_PRECISION: usize = 3;
+
+/// Amounts compare by their bit pattern, NaN equal to itself and negative
+/// zero to zero, so facts are `Eq` and hash like [`Recipe`] does.
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct NutritionFacts(pub(crate) BTreeMap<Nutrition, f64>);
+
+impl FromIterator<(Nutrition, f64)> for NutritionFacts {
+    /// Facts of the given amounts, later amounts of a nutrient replacing
+    /// earlier ones.
+    fn from_iter<I: IntoIterator<Item = (Nutrition, f64)>>(amounts: I) -> Self {
+        NutritionFacts(amounts.into_iter().collect())
+    }
+}
+
+/// How many times the declared weight of its dish an ingredient may weigh
+/// before the weight is taken for a mistake.
+pub const OVERWEIGHT_INGREDIENT_RATIO: f64 = 2.0;
+
+/// Nutrients that together make up the weight of a product, fiber and
+/// sugars being part of the carbohydrates.
+const MASS_NUTRIENTS: [Nutrition; 4] = [
+    Nutrition::Proteins,
+    Nutrition::Fats,
+    Nutrition::Carbohydrates,
+    Nutrition::Water,
+];
 
 impl NutritionFacts {
-    fn into_inner(self) -> HashMap<Nutrition, f64> {
-        self.0
+    /// Facts without any nutrient.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the amount of `nutrient`, returning the amount it replaces.
+    pub fn insert(&mut self, nutrient: Nutrition, amount: f64) -> Option<f64> {
+        self.0.insert(nutrient, amount)
+    }
+
+    /// Amount of `nutrient` per the basis the facts were computed for.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nutritions::{Nutrition, Recipe};
+    ///
+    /// let recipe = Recipe::from_yaml_str(
+    ///     r#"
+    /// products:
+    ///   - name: Oil
+    ///     facts:
+    ///       Energy: 900
+    /// dish:
+    ///   ingredients:
+    ///     - product: Oil
+    ///       amount: 50
+    /// "#,
+    /// )?;
+    /// let facts = futures::executor::block_on(recipe.get_total_nutrition())?;
+    ///
+    /// assert_eq!(facts.get(Nutrition::Energy), Some(450.0));
+    /// assert_eq!(facts.get(Nutrition::Fats), None);
+    /// # Ok::<(), nutritions::RecipeError>(())
+    /// ```
+    pub fn get(&self, nutrient: Nutrition) -> Option<f64> {
+        self.0.get(&nutrient).copied()
+    }
+
+    /// Grams of macronutrients and water, which can't exceed the weight the
+    /// facts are given for.
+    pub fn mass(&self) -> f64 {
+        MASS_NUTRIENTS
+            .iter()
+            .map(|nutrient| self.0.get(nutrient).copied().unwrap_or(0.0))
+            .sum()
+    }
+
+    /// Present nutrients with their amounts in display order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nutritions::{Nutrition, Recipe};
+    ///
+    /// let recipe = Recipe::from_yaml_str(
+    ///     r#"
+    /// products:
+    ///   - name: Milk
+    ///     facts:
+    ///       Fats: 3.2
+    ///       Energy: 60
+    /// dish:
+    ///   ingredients:
+    ///     - product: Milk
+    ///       amount: 100
+    /// "#,
+    /// )?;
+    /// let facts = futures::executor::block_on(recipe.get_nutrition_facts())?;
+    ///
+    /// let energy = facts.iter().find(|(nutrient, _)| **nutrient == Nutrition::Energy);
+    /// assert_eq!(energy, Some((&Nutrition::Energy, 60.0)));
+    /// assert_eq!(
+    ///     facts.nutrients().collect::<Vec<_>>(),
+    ///     [&Nutrition::Energy, &Nutrition::Fats]
+    /// );
+    /// # Ok::<(), nutritions::RecipeError>(())
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (&Nutrition, f64)> {
+        self.sorted().into_iter()
+    }
+
+    /// Present nutrients in display order.
+    pub fn nutrients(&self) -> impl Iterator<Item = &Nutrition> {
+        self.iter().map(|(nutrient, _)| nutrient)
+    }
+
+    /// Facts written for version control: a `name: amount unit` line for
+    /// every present nutrient in display order, amounts with
+    /// [`CANONICAL_PRECISION`] decimals and every line ending with a newline.
+    /// Equal facts are always written the same, so changes of computed
+    /// results diff line by line.
+    pub fn to_canonical_string(&self) -> String {
+        let mut out = String::new();
+        for (nutrient, amount) in self.iter() {
+            let amount = format!("{:.*}", CANONICAL_PRECISION, amount);
+            // Amounts rounding to zero from below would be written as `-0.000`.
+            let amount = match amount.strip_prefix('-') {
+                Some(zero) if zero.bytes().all(|digit| matches!(digit, b'0' | b'.')) => zero,
+                _ => &amount,
+            };
+            out.push_str(nutrient.name());
+            out.push_str(": ");
+            out.push_str(amount);
+            if !nutrient.unit().is_empty() {
+                out.push(' ');
+                out.push_str(nutrient.unit());
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Present nutrients with known ones first in display order, followed by
+    /// custom ones sorted by name.
+    pub(crate) fn sorted(&self) -> Vec<(&Nutrition, f64)> {
+        self.0.iter().map(|(item, value)| (item, *value)).collect()
+    }
+
+    /// Present nutrients listed in `order` first, then the rest like in
+    /// [`NutritionFacts::sorted`].
+    pub(crate) fn sorted_by(&self, order: &[Nutrition]) -> Vec<(&Nutrition, f64)> {
+        let mut items: Vec<(&Nutrition, f64)> = Vec::new();
+        for item in order {
+            if let Some((item, value)) = self.0.get_key_value(item) {
+                if !items.iter().any(|(listed, _)| *listed == item) {
+                    items.push((item, *value));
+                }
+            }
+        }
+        let others: Vec<_> = self
+            .sorted()
+            .into_iter()
+            .filter(|(item, _)| !order.contains(item))
+            .collect();
+        items.extend(others);
+        items
     }
 }
 
-impl fmt::Display for NutritionFacts {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for item in enum_iterator::all::<Nutrition>() {
-            if let Some(value) = self.0.get(&item) {
-                write!(f, "{:?}:  {:.2}\n", item, value)?;
+/// Serialized as a map in display order, so the output is stable.
+impl Serialize for NutritionFacts {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.sorted())
+    }
+}
+
+impl Add for NutritionFacts {
+    type Output = NutritionFacts;
+
+    fn add(mut self, other: NutritionFacts) -> NutritionFacts {
+        for (nutrient, amount) in other.0 {
+            *self.0.entry(nutrient).or_insert(0.0) += amount;
+        }
+        self
+    }
+}
+
+/// What subtracting more of a nutrient than there is results in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SubtractPolicy {
+    /// No amount goes below zero, as no real food has negative nutrients.
+    #[default]
+    Clamp,
+    /// Amounts may go negative, showing by how much the subtracted facts
+    /// exceed the others.
+    Negative,
+}
+
+impl NutritionFacts {
+    /// Subtracts amounts of `other` from the matching ones, as missing
+    /// nutrients had none, going below zero only as `policy` allows.
+    pub fn subtract(mut self, other: &NutritionFacts, policy: SubtractPolicy) -> NutritionFacts {
+        for (nutrient, amount) in &other.0 {
+            let result = self.0.entry(nutrient.clone()).or_insert(0.0);
+            *result -= amount;
+            if policy == SubtractPolicy::Clamp {
+                *result = result.max(0.0);
             }
         }
-        Ok(())
+        self
     }
 }
-#[derive(Deserialize)]
+
+/// How computed facts are rounded once every ingredient is summed up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RoundingPolicy {
+    /// Amounts are kept as computed.
+    #[default]
+    Exact,
+    /// Energy to whole kcal, sodium to whole mg and nutrients weighed in
+    /// grams to 0.1g, as labels give them. Custom nutrients are kept as
+    /// computed.
+    Label,
+}
+
+impl RoundingPolicy {
+    /// Decimal places amounts of `nutrient` are rounded to, `None` when
+    /// they're kept as computed.
+    pub fn decimals(self, nutrient: &Nutrition) -> Option<i32> {
+        match (self, nutrient.unit()) {
+            (RoundingPolicy::Exact, _) | (_, "") => None,
+            (RoundingPolicy::Label, "g") => Some(1),
+            (RoundingPolicy::Label, _) => Some(0),
+        }
+    }
+}
+
+impl NutritionFacts {
+    /// Same facts with amounts rounded as `policy` asks.
+    pub fn rounded(mut self, policy: RoundingPolicy) -> NutritionFacts {
+        for (nutrient, amount) in &mut self.0 {
+            if let Some(decimals) = policy.decimals(nutrient) {
+                let factor = 10f64.powi(decimals);
+                *amount = (*amount * factor).round() / factor;
+            }
+        }
+        self
+    }
+}
+
+/// Subtracts with [`SubtractPolicy::Clamp`], the default policy.
+impl Sub for NutritionFacts {
+    type Output = NutritionFacts;
+
+    fn sub(self, other: NutritionFacts) -> NutritionFacts {
+        self.subtract(&other, SubtractPolicy::default())
+    }
+}
+
+impl Mul<f64> for NutritionFacts {
+    type Output = NutritionFacts;
+
+    fn mul(mut self, factor: f64) -> NutritionFacts {
+        for amount in self.0.values_mut() {
+            *amount *= factor;
+        }
+        self
+    }
+}
+
+/// Amounts are written with the precision of the formatter, two decimals
+/// by default.
+impl fmt::Display for NutritionFacts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let style = Style {
+            precision: f.precision().unwrap_or(DEFAULT_PRECISION),
+            ..Style::default()
+        };
+        f.write_str(&format::text(self, &style))
+    }
+}
+/// Products and the dishes they make, as a recipe file describes them.
+///
+/// Recipes are equal when their contents are, wherever they were read from:
+/// their products, aliases, dishes and metadata are compared, along with the
+/// libraries they include until those are merged. Once merged, only the
+/// products merged from them count, however their paths are written. The
+/// paths of the file and of its merged libraries aren't compared, and
+/// neither is the order of aliases. Amounts compare and hash by their bit
+/// pattern, both zeros alike and every NaN alike, so that equal recipes hash
+/// the same.
+#[derive(Clone, Deserialize, JsonSchema)]
 pub struct Recipe {
+    /// Product library files whose products are merged into the recipe's.
+    #[serde(default)]
+    include: Vec<PathBuf>,
     products: Vec<Product>,
-    dish: Dish,
+    /// Names ingredients may use instead of the names of their products.
+    #[serde(default)]
+    aliases: HashMap<String, String>,
+    #[serde(flatten)]
+    dishes: Dishes,
+    #[serde(default)]
+    metadata: Metadata,
+    /// Canonical path of the file this recipe was loaded from.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    /// Canonical paths of the included libraries, once merged.
+    #[serde(skip)]
+    libraries: Vec<PathBuf>,
+}
+
+/// Descriptive fields of a recipe, kept along but not computed with.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize, JsonSchema)]
+pub struct Metadata {
+    /// Where the recipe comes from, like a book or a URL.
+    pub source: Option<String>,
+    pub author: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+}
+
+impl Metadata {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
 }
 
+/// File sharing products between recipes.
 #[derive(Deserialize)]
+struct ProductLibrary {
+    products: Vec<Product>,
+}
+
+/// Either the single dish of a recipe or the named components of a composed
+/// one, like the parts of a plate.
+#[derive(Clone, PartialEq, Eq, Hash, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum Dishes {
+    Dish(Dish),
+    Dishes(Vec<Component>),
+}
+
+impl Dishes {
+    /// Components of the dish, a single dish named after the file stem of
+    /// `path`.
+    fn into_components(self, path: Option<&Path>) -> impl Iterator<Item = Component> {
+        let components = match self {
+            Dishes::Dish(dish) => vec![Component {
+                name: path
+                    .and_then(Path::file_stem)
+                    .map_or_else(|| "dish".into(), |stem| stem.to_string_lossy().into_owned()),
+                dish,
+            }],
+            Dishes::Dishes(components) => components,
+        };
+        components.into_iter()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Deserialize, JsonSchema)]
+struct Component {
+    name: String,
+    #[serde(flatten)]
+    dish: Dish,
+}
+
+#[derive(Clone, Default, Deserialize, JsonSchema)]
 pub struct Dish {
     ingredients: Vec<Ingredient>,
     weight: Option<f64>,
+    /// Weight of the dish after cooking, takes precedence over `weight` when
+    /// both are given.
+    cooked_weight: Option<f64>,
+    /// How many servings the dish is split into.
+    servings: Option<f64>,
 }
 
-#[derive(Deserialize)]
+impl Dish {
+    /// Weight the facts per 100g are computed for, the raw weight of the
+    /// ingredients unless the dish declares its own.
+    fn final_weight(&self, total_ingredients_weight: f64) -> f64 {
+        self.cooked_weight
+            .or(self.weight)
+            .unwrap_or(total_ingredients_weight)
+    }
+}
+
+#[derive(Clone, Deserialize, JsonSchema)]
+#[serde(try_from = "WrittenIngredient")]
+#[schemars(with = "WrittenIngredient")]
 pub struct Ingredient {
     product: String,
     amount: f64,
+    unit: Option<String>,
+    to_taste: bool,
 }
 
-#[derive(Deserialize)]
+/// Ingredient as written in files, where only ingredients to taste may omit
+/// their amount.
+#[derive(Deserialize, JsonSchema)]
+struct WrittenIngredient {
+    product: String,
+    /// Zero, or omitted, for ingredients to taste.
+    amount: Option<f64>,
+    /// Symbol of the unit `amount` is given in, grams when omitted.
+    #[serde(default)]
+    #[schemars(schema_with = "schema::unit")]
+    unit: Option<String>,
+    /// Lists the ingredient in breakdowns and allergens without it adding
+    /// any weight or nutrients, as spices to taste.
+    #[serde(default)]
+    to_taste: bool,
+}
+
+impl TryFrom<WrittenIngredient> for Ingredient {
+    type Error = String;
+
+    fn try_from(written: WrittenIngredient) -> Result<Self, Self::Error> {
+        let amount = match (written.amount, written.to_taste) {
+            (Some(amount), _) => amount,
+            (None, true) => 0.0,
+            (None, false) => {
+                return Err(format!("missing amount of ingredient {}", written.product))
+            }
+        };
+        Ok(Self {
+            product: written.product,
+            amount,
+            unit: written.unit,
+            to_taste: written.to_taste,
+        })
+    }
+}
+
+impl Ingredient {
+    /// Ingredient weighing `amount` grams.
+    pub fn new(product: impl Into<String>, amount: f64) -> Self {
+        Self {
+            product: product.into(),
+            amount,
+            unit: None,
+            to_taste: false,
+        }
+    }
+
+    /// Ingredient added to taste, weighing nothing.
+    pub fn to_taste(product: impl Into<String>) -> Self {
+        Self {
+            to_taste: true,
+            ..Self::new(product, 0.0)
+        }
+    }
+
+    /// Whether the ingredient is added to taste, either said to be or
+    /// weighing nothing.
+    pub fn is_to_taste(&self) -> bool {
+        self.to_taste || self.amount == 0.0
+    }
+
+    /// Same ingredient with `amount` given in the unit of `symbol`.
+    pub fn unit(mut self, symbol: impl Into<String>) -> Self {
+        self.unit = Some(symbol.into());
+        self
+    }
+
+    fn parsed_unit(&self) -> Result<Unit, RecipeError> {
+        match self.unit {
+            Some(ref symbol) => Unit::from_symbol(symbol).ok_or_else(|| RecipeError::UnknownUnit {
+                ingredient: self.product.clone(),
+                unit: symbol.clone(),
+            }),
+            None => Ok(Unit::Gram),
+        }
+    }
+
+    /// Whether the amount counts whole dishes of the sub-recipe of
+    /// `product`, rather than weighing it.
+    fn counts_dishes(&self, product: &Product) -> Result<bool, RecipeError> {
+        Ok(matches!(
+            product.nutrition_data,
+            NutritionData::Recipe(_, RecipeBasis::Whole)
+        ) && (self.unit.is_none() || self.parsed_unit()? == Unit::Piece))
+    }
+
+    /// Grams of `product` the ingredient weighs, percentages being of
+    /// `dish_weight`.
+    fn grams(&self, product: &Product, dish_weight: Option<f64>) -> Result<f64, RecipeError> {
+        if self.is_to_taste() {
+            return Ok(0.0);
+        }
+        if self.counts_dishes(product)? {
+            return Err(RecipeError::UnknownRecipeWeight {
+                product: product.name.clone(),
+            });
+        }
+        let unit = self.parsed_unit()?;
+        if unit == Unit::Percent {
+            return dish_weight
+                .map(|weight| weight * self.amount / 100.0)
+                .ok_or_else(|| RecipeError::MissingDishWeight {
+                    ingredient: self.product.clone(),
+                });
+        }
+        unit.to_grams(
+            self.amount,
+            product.density_g_per_ml,
+            product.grams_per_piece,
+            product.serving_weight_g,
+        )
+        .ok_or_else(|| match unit {
+            Unit::Piece => RecipeError::MissingPieceWeight {
+                product: product.name.clone(),
+            },
+            Unit::Serving => RecipeError::MissingServingWeight {
+                product: product.name.clone(),
+            },
+            _ => RecipeError::MissingDensity {
+                product: product.name.clone(),
+            },
+        })
+    }
+}
+
+/// Food ingredients are measured out of, with its nutrition facts or where
+/// they come from.
+#[derive(Clone, Deserialize, JsonSchema)]
 pub struct Product {
     name: String,
+    /// Needed to measure the product by volume.
+    density_g_per_ml: Option<f64>,
+    /// Needed to count the product in pieces.
+    grams_per_piece: Option<f64>,
+    /// Needed to measure the product in servings, as databases that give
+    /// facts per serving do.
+    serving_weight_g: Option<f64>,
+    /// Amount of the product its facts are given for, 100g when omitted.
+    per: Option<Basis>,
+    /// Shorthand for `per` as data sources name their basis, like `per_oz`.
+    basis: Option<FactsBasis>,
+    /// Factor all nutrients of the product are multiplied by when it's
+    /// used, like 0.5 for a concentrate used diluted by half. 1 when
+    /// omitted.
+    scale: Option<f64>,
+    price_per_100g: Option<f64>,
+    #[serde(default)]
+    allergens: Vec<String>,
     #[serde(flatten)]
     nutrition_data: NutritionData,
 }
 
+#[derive(Clone, Deserialize, JsonSchema)]
+pub struct Basis {
+    amount: f64,
+    /// Symbol of the unit `amount` is given in, grams when omitted.
+    #[serde(default)]
+    #[schemars(schema_with = "schema::unit")]
+    unit: Option<String>,
+}
+
+/// Amounts facts are commonly given for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, JsonSchema)]
+pub enum FactsBasis {
+    #[serde(rename = "per_100g")]
+    HundredGrams,
+    /// An ounce of 28.35g, as US data sources give facts for.
+    #[serde(rename = "per_oz")]
+    Ounce,
+}
+
+impl FactsBasis {
+    fn to_basis(self) -> Basis {
+        match self {
+            FactsBasis::HundredGrams => Basis {
+                amount: 100.0,
+                unit: None,
+            },
+            FactsBasis::Ounce => Basis {
+                amount: 1.0,
+                unit: Some("oz".into()),
+            },
+        }
+    }
+}
+
 impl Product {
-    async fn get_nutrition_facts(&self) -> Result<NutritionFacts, Box<dyn Error>> {
+    /// Product with nutrition facts per 100g.
+    pub fn new(name: impl Into<String>, facts: impl IntoIterator<Item = (Nutrition, f64)>) -> Self {
+        Self::with_data(name, NutritionData::Facts(facts.into_iter().collect()))
+    }
+
+    /// Product made by the recipe in the file at `path`.
+    pub fn from_recipe(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self::with_data(
+            name,
+            NutritionData::Recipe(path.into(), RecipeBasis::HundredGrams),
+        )
+    }
+
+    /// Product made by the recipe in the file at `path`, amounts of which
+    /// count whole dishes of it.
+    pub fn from_whole_recipe(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self::with_data(name, NutritionData::Recipe(path.into(), RecipeBasis::Whole))
+    }
+
+    /// Product mixed from other products of the recipe, with facts per 100g
+    /// averaged by their percentages.
+    pub fn from_blend(
+        name: impl Into<String>,
+        components: impl IntoIterator<Item = BlendComponent>,
+    ) -> Self {
+        Self::with_data(name, NutritionData::Blend(components.into_iter().collect()))
+    }
+
+    fn with_data(name: impl Into<String>, nutrition_data: NutritionData) -> Self {
+        Self {
+            name: name.into(),
+            density_g_per_ml: None,
+            grams_per_piece: None,
+            serving_weight_g: None,
+            per: None,
+            basis: None,
+            price_per_100g: None,
+            scale: None,
+            allergens: Vec::new(),
+            nutrition_data,
+        }
+    }
+
+    pub fn density_g_per_ml(mut self, density: f64) -> Self {
+        self.density_g_per_ml = Some(density);
+        self
+    }
+
+    pub fn grams_per_piece(mut self, grams: f64) -> Self {
+        self.grams_per_piece = Some(grams);
+        self
+    }
+
+    pub fn serving_weight_g(mut self, grams: f64) -> Self {
+        self.serving_weight_g = Some(grams);
+        self
+    }
+
+    /// Same product with facts given for `amount` in the unit of `symbol`.
+    pub fn per(mut self, amount: f64, symbol: impl Into<String>) -> Self {
+        self.per = Some(Basis {
+            amount,
+            unit: Some(symbol.into()),
+        });
+        self
+    }
+
+    /// Same product with facts given for `basis`.
+    pub fn basis(mut self, basis: FactsBasis) -> Self {
+        self.basis = Some(basis);
+        self
+    }
+
+    /// Same product with nutrients multiplied by `factor` when it's used.
+    pub fn scale(mut self, factor: f64) -> Self {
+        self.scale = Some(factor);
+        self
+    }
+
+    pub fn price_per_100g(mut self, price: f64) -> Self {
+        self.price_per_100g = Some(price);
+        self
+    }
+
+    pub fn allergen(mut self, allergen: impl Into<String>) -> Self {
+        self.allergens.push(allergen.into());
+        self
+    }
+
+    /// Factor converting the declared facts to facts per 100g.
+    fn to_hundred_grams(&self) -> Result<f64, RecipeError> {
+        match self
+            .per
+            .clone()
+            .or_else(|| self.basis.map(FactsBasis::to_basis))
+        {
+            None => Ok(1.0),
+            Some(ref basis) => {
+                let basis = Ingredient {
+                    product: self.name.clone(),
+                    amount: basis.amount,
+                    unit: basis.unit.clone(),
+                    to_taste: false,
+                };
+                Ok(100.0 / basis.grams(self, None)?)
+            }
+        }
+    }
+
+    /// Facts per 100g of a product declaring its facts, failing with
+    /// `UnresolvedProduct` for products made by recipes or blends.
+    pub(crate) fn facts_per_100g(&self) -> Result<NutritionFacts, RecipeError> {
+        let facts =
+            self.nutrition_data
+                .nominal_facts()
+                .ok_or_else(|| RecipeError::UnresolvedProduct {
+                    product: self.name.clone(),
+                })?;
+        Ok(self.scaled(facts * self.to_hundred_grams()?))
+    }
+
+    /// Facts per 100g of the product multiplied by its `scale`.
+    fn scaled(&self, facts: NutritionFacts) -> NutritionFacts {
+        match self.scale {
+            Some(scale) => facts * scale,
+            None => facts,
+        }
+    }
+
+    /// Converts facts declared for another `per` or `basis` to facts per
+    /// 100g, so that products are computed with alike facts.
+    fn normalize_basis(&mut self) -> Result<(), RecipeError> {
+        if self.per.is_none() && self.basis.is_none() {
+            return Ok(());
+        }
+        let factor = self.to_hundred_grams()?;
+        match self.nutrition_data {
+            NutritionData::Facts(ref mut facts) => *facts = facts.clone() * factor,
+            NutritionData::Range(ref mut range) => *range = range.clone() * factor,
+            NutritionData::Recipe(..) | NutritionData::Blend(_) => return Ok(()),
+        }
+        debug!("Converted facts of {} to per 100g by {}", self.name, factor);
+        self.per = None;
+        self.basis = None;
+        Ok(())
+    }
+
+    /// Whether both products are described the same, their names matching
+    /// ignoring case.
+    fn same_as(&self, other: &Product) -> bool {
+        self.is_named(&other.name)
+            && self.density_g_per_ml == other.density_g_per_ml
+            && self.grams_per_piece == other.grams_per_piece
+            && self.serving_weight_g == other.serving_weight_g
+            && self.per == other.per
+            && self.basis == other.basis
+            && self.scale == other.scale
+            && self.price_per_100g == other.price_per_100g
+            && self.allergens == other.allergens
+            && self.nutrition_data == other.nutrition_data
+    }
+
+    /// Names match ignoring case and surrounding whitespace.
+    fn is_named(&self, name: &str) -> bool {
+        normalize_name(&self.name) == normalize_name(name)
+    }
+
+    /// Nutrition data of the product with the sub-recipe path, if any,
+    /// canonicalized. Relative paths are looked up as by [`locate`] from
+    /// `base`, the directory of the referencing recipe. Facts are per 100g
+    /// since recipes normalize them when they're loaded.
+    async fn canonical_nutrition_data(
+        &self,
+        base: Option<&Path>,
+    ) -> Result<NutritionData, RecipeError> {
         match self.nutrition_data {
-            NutritionData::Facts(ref facts) => Ok(facts.clone()),
-            NutritionData::Recipe(ref path) => {
-                let recipe = Recipe::read_from_file(&path).await?;
-                recipe.get_nutrition_facts().await
+            NutritionData::Facts(ref facts) => Ok(NutritionData::Facts(facts.clone())),
+            NutritionData::Range(ref range) => Ok(NutritionData::Range(range.clone())),
+            NutritionData::Recipe(ref path, basis) => {
+                Ok(NutritionData::Recipe(locate(path, base).await?, basis))
             }
+            NutritionData::Blend(ref components) => Ok(NutritionData::Blend(components.clone())),
         }
     }
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum NutritionData {
-    Facts(NutritionFacts),
-    Recipe(PathBuf),
+/// Price of the ingredients of a recipe.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cost {
+    pub total: f64,
+    /// Known when every dish declares its servings.
+    pub per_serving: Option<f64>,
 }
 
-#[derive(Deserialize, PartialEq, Eq, Hash, Sequence, Debug, Clone, Copy)]
-pub enum Nutrition {
-    Energy,
-    Proteins,
-    Fats,
-    Carbohydrates,
+/// Facts per 100g of a recipe together with the weights they were computed
+/// from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComputedRecipe {
+    pub facts: NutritionFacts,
+    /// Grams the raw ingredients weigh.
+    pub raw_weight: f64,
+    /// Grams the finished dishes weigh, the facts are given per 100g of it.
+    pub dish_weight: f64,
 }
 
-impl Recipe {
-    pub async fn read_from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
-        let mut file = tokio::fs::File::open(path).await?;
+/// How an ingredient adds to its dish, a step of an [`Explanation`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct IngredientStep {
+    /// Name of the product as the ingredient refers to it.
+    pub product: String,
+    pub amount: f64,
+    /// Symbol of the unit the amount is given in, grams when omitted.
+    pub unit: Option<String>,
+    pub to_taste: bool,
+    /// Grams the amount weighs.
+    pub grams: f64,
+    /// Facts per 100g of the product, `None` for ingredients weighing
+    /// nothing.
+    pub facts: Option<NutritionFacts>,
+    /// Nutrients the amount adds to the dish.
+    pub contribution: NutritionFacts,
+}
 
-        let mut file_contents: String = "".into();
-        file.read_to_string(&mut file_contents).await?;
-        let recipe: Recipe = serde_yaml::from_str(&file_contents)?;
-        Ok(recipe)
-    }
+/// Account of how facts of a recipe are computed: what every counted
+/// ingredient adds and how their sum is normalized to 100g of the dish.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explanation {
+    /// Ingredients of every dish, one dish after another.
+    pub ingredients: Vec<IngredientStep>,
+    /// Sum of the contributions of the ingredients.
+    pub totals: NutritionFacts,
+    pub computed: ComputedRecipe,
+}
 
-    #[async_recursion]
-    pub async fn get_nutrition_facts(&self) -> Result<NutritionFacts, Box<dyn Error>> {
-        let mut totals_for_dish: HashMap<Nutrition, f64> = HashMap::new();
-        let mut total_ingredients_weight = 0.0;
-        for ingredient in &self.dish.ingredients {
-            if let Some(product) = self.products.iter().find(|p| p.name == ingredient.product) {
-                total_ingredients_weight += ingredient.amount;
-                for (nutrient, amount) in &product.get_nutrition_facts().await?.into_inner() {
-                    let this_amount = amount / 100.0 * ingredient.amount;
-                    println!(
-                        "add {} {:?} {:?} {}g = {}",
-                        product.name,
-                        nutrient,
-                        amount / 100.0,
-                        ingredient.amount,
-                        this_amount
-                    );
-                    totals_for_dish
-                        .entry(*nutrient)
-                        .and_modify(|v| *v += this_amount)
-                        .or_insert(this_amount);
-                }
-            } else {
-                panic!(
-                    "Cannot find ingredient in recipe: {} possible products: {}",
-                    ingredient.product,
-                    self.products
-                        .iter()
-                        .map(|p| p.name.clone())
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                )
-            }
-        }
-        println!(
-            "Totals for raw ingredients {:?} {}",
-            totals_for_dish, total_ingredients_weight
-        );
+pub const DEFAULT_MAX_DEPTH: usize = 32;
+
+/// Options controlling how recipe files are read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Fails with `UnknownFields` for fields no recipe has instead of
+    /// ignoring them, so that misspelled ones like `wieght` are caught.
+    pub strict: bool,
+    /// Reads amounts of products' facts written with a decimal comma, like
+    /// `10,5`, as European data often has them.
+    pub decimal_comma: bool,
+    /// Supplies products a recipe doesn't define from the products file of
+    /// [`default_database_path`], if there's one.
+    pub default_database: bool,
+    /// Fails with `FileReference` for recipes including libraries or made of
+    /// sub-recipes, so that recipes from untrusted sources can't have files
+    /// read.
+    pub no_files: bool,
+}
 
+/// Options controlling how nutrition facts of a recipe are computed.
+#[derive(Clone, Debug)]
+pub struct ComputeOptions {
+    /// How many levels of nested sub-recipes may be loaded.
+    pub max_depth: usize,
+    /// Estimates energy of products that declare only macronutrients.
+    pub estimate_energy: bool,
+    /// Skips ingredients whose product is missing instead of failing with
+    /// `IngredientNotFound`. Skipped ingredients count neither their
+    /// nutrients nor their weight.
+    pub allow_missing: bool,
+    /// Collects the time spent on sub-recipes and aggregation when given.
+    pub timings: Option<Arc<Timings>>,
+    /// Fails with `MissingWeight` for dishes that don't declare their weight
+    /// instead of taking the weight of their raw ingredients, and with
+    /// `OverweightIngredient` for the ingredients
+    /// [`Recipe::overweight_ingredients`] lists.
+    pub strict_weights: bool,
+    /// Rounding of the computed facts, sub-recipes being summed up exactly.
+    pub rounding: RoundingPolicy,
+    /// Bound of ranged amounts of products to use, their midpoint when not
+    /// given.
+    pub bound: Option<Bound>,
+    /// How sub-recipes are read.
+    pub parse: ParseOptions,
+    /// Ingredients of the recipe the facts are computed from, those of its
+    /// sub-recipes always counting.
+    pub ingredients: IngredientFilter,
+}
 
-        let weight_to_hundred = self
-            .dish
-            .weight
-            .map(|dish_weight| 100.0 / dish_weight)
-            .unwrap_or(100.0 / total_ingredients_weight);
+/// Ingredients facts are computed from, named like products are looked up.
+/// Dishes are weighed as the share of their weight the raw weight of the
+/// counted ingredients makes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum IngredientFilter {
+    #[default]
+    All,
+    /// Only ingredients of the named products.
+    Only(Vec<String>),
+    /// Every ingredient except those of the named products.
+    Exclude(Vec<String>),
+}
 
-        Ok(NutritionFacts(
-            totals_for_dish
-                .into_iter()
-                .map(|(k, a)| (k, a  * weight_to_hundred))
-                .collect(),
-        ))
+impl Default for ComputeOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            estimate_energy: false,
+            allow_missing: false,
+            timings: None,
+            strict_weights: false,
+            rounding: RoundingPolicy::default(),
+            bound: None,
+            parse: ParseOptions::default(),
+            ingredients: IngredientFilter::default(),
+        }
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::{Ingredient, Nutrition, NutritionFacts, Product, Recipe};
+impl ComputeOptions {
+    /// Facts of a product per 100g as these options use them.
+    fn product_facts(&self, facts: NutritionFacts) -> NutritionFacts {
+        if self.estimate_energy {
+            facts.with_estimated_energy()
+        } else {
+            facts
+        }
+    }
+}
 
-    #[test]
-    fn calculate1() {
-        let oil = Product {
-            name: "Oil".into(),
-            facts: [(Nutrition::Energy, 1000.0)].into_iter().collect(),
-        };
-        let recipe = Recipe {
-            dish: super::Dish {
-                ingredients: vec![Ingredient {
-                    product: "Oil".into(),
-                    amount: 10.0,
-                }],
-                weight: 20.0,
-            },
-            products: vec![oil],
-        };
+/// State threaded through the recursive resolution of sub-recipes.
+struct Resolution<'a> {
+    options: &'a ComputeOptions,
+    /// Canonical paths of the recipe files currently being resolved,
+    /// outermost first.
+    chain: Vec<PathBuf>,
+    /// Nesting level of sub-recipes, the top-level recipe is at 0.
+    depth: usize,
+    /// Facts of sub-recipes already computed during this resolution, keyed by
+    /// canonical path. Sibling branches resolved concurrently may still both
+    /// load a file they share.
+    cache: Arc<Mutex<HashMap<PathBuf, ComputedRecipe>>>,
+}
 
-        let facts = recipe.get_nutrition_facts();
+impl<'a> Resolution<'a> {
+    fn new(options: &'a ComputeOptions, path: Option<&Path>) -> Self {
+        Self {
+            options,
+            chain: path.map(Path::to_path_buf).into_iter().collect(),
+            depth: 0,
+            cache: Default::default(),
+        }
+    }
 
-        assert_eq!(
-            facts,
-            NutritionFacts([(Nutrition::Energy, 500.0)].into_iter().collect())
+    fn enter(&self, path: PathBuf) -> Result<Self, RecipeError> {
+        if self.chain.contains(&path) {
+            return Err(RecipeError::CycleDetected { path });
+        }
+        if self.depth >= self.options.max_depth {
+            return Err(RecipeError::MaxDepthExceeded {
+                depth: self.options.max_depth,
+                path,
+            });
+        }
+        let mut chain = self.chain.clone();
+        chain.push(path);
+        Ok(Self {
+            options: self.options,
+            chain,
+            depth: self.depth + 1,
+            cache: self.cache.clone(),
+        })
+    }
+
+    /// Reads the sub-recipe at `path`, timing it when asked to.
+    async fn read(&self, path: &Path) -> Result<Recipe, RecipeError> {
+        let format = InputFormat::from_path(path);
+        Recipe::load(
+            path,
+            format,
+            self.options.timings.as_deref(),
+            self.options.parse,
         )
+        .await
     }
-    #[test]
-    #[should_panic(
-        expected = "Cannot find ingredient in recipe: cabbage possible products: Oil, Milk"
-    )]
-    fn fail_not_found() {
-        let milk = Product {
-            name: "Milk".into(),
-            facts: [(Nutrition::Energy, 1000.0)].into_iter().collect(),
-        };
-        let oil = Product {
-            name: "Oil".into(),
-            facts: [(Nutrition::Energy, 1000.0)].into_iter().collect(),
-        };
-        let recipe = Recipe {
-            dish: super::Dish {
-                ingredients: vec![Ingredient {
-                    product: "cabbage".into(),
-                    amount: 10.0,
-                }],
-                weight: 20.0,
+
+    /// Loads the sub-recipe at the canonical `path` and computes its facts,
+    /// reusing facts already computed for the same path.
+    async fn resolve(&self, path: PathBuf) -> Result<ComputedRecipe, RecipeError> {
+        let resolution = self.enter(path.clone())?;
+        if let Some(computed) = self.cached(&path) {
+            return Ok(computed);
+        }
+        let computed = async {
+            let recipe = resolution.read(&path).await?;
+            recipe.compute_in(&resolution).await
+        }
+        .await
+        .map_err(|err| resolution.wrap(err))?;
+        self.cache(path, computed.clone());
+        Ok(computed)
+    }
+
+    /// Records the chain of sub-recipes an error came from, unless a more
+    /// deeply nested sub-recipe already did.
+    fn wrap(&self, err: RecipeError) -> RecipeError {
+        match err {
+            RecipeError::InRecipe { .. } => err,
+            err => RecipeError::InRecipe {
+                chain: self.chain.clone(),
+                source: Box::new(err),
             },
-            products: vec![oil, milk],
+        }
+    }
+
+    // A poisoned cache still holds only complete facts, so it stays usable.
+    fn cached(&self, path: &Path) -> Option<ComputedRecipe> {
+        let cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.get(path).cloned()
+    }
+
+    fn cache(&self, path: PathBuf, computed: ComputedRecipe) {
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.insert(path, computed);
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(from = "WrittenNutritionData")]
+#[schemars(with = "WrittenNutritionData")]
+pub enum NutritionData {
+    Facts(NutritionFacts),
+    /// Facts some amounts of which are given as ranges.
+    Range(NutritionRange),
+    /// Facts of the dish the recipe in the file makes.
+    Recipe(PathBuf, RecipeBasis),
+    /// Mix of other products of the recipe, whose percentages add up to 100.
+    Blend(Vec<BlendComponent>),
+}
+
+impl NutritionData {
+    /// Facts the data declares, halfway between the bounds of ranges.
+    fn nominal_facts(&self) -> Option<NutritionFacts> {
+        match self {
+            NutritionData::Facts(facts) => Some(facts.clone()),
+            NutritionData::Range(range) => Some(range.at(None)),
+            NutritionData::Recipe(..) | NutritionData::Blend(_) => None,
+        }
+    }
+}
+
+/// Nutrition data as written in files, where any amount of facts may be a
+/// range.
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum WrittenNutritionData {
+    Facts(#[schemars(schema_with = "schema::facts")] BTreeMap<Nutrition, Amount>),
+    Recipe(SubRecipe),
+    Blend(Vec<BlendComponent>),
+}
+
+impl From<WrittenNutritionData> for NutritionData {
+    fn from(data: WrittenNutritionData) -> Self {
+        match data {
+            WrittenNutritionData::Facts(amounts) => {
+                let range = NutritionRange::from_amounts(&amounts);
+                if amounts.values().all(Amount::is_exact) {
+                    NutritionData::Facts(range.min)
+                } else {
+                    NutritionData::Range(range)
+                }
+            }
+            WrittenNutritionData::Recipe(SubRecipe::Path(path)) => {
+                NutritionData::Recipe(path, RecipeBasis::default())
+            }
+            WrittenNutritionData::Recipe(SubRecipe::WithBasis { path, basis }) => {
+                NutritionData::Recipe(path, basis)
+            }
+            WrittenNutritionData::Blend(components) => NutritionData::Blend(components),
+        }
+    }
+}
+
+/// Sub-recipe file a product is made by, either its path alone or along
+/// with the basis of the product.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum SubRecipe {
+    Path(PathBuf),
+    WithBasis {
+        path: PathBuf,
+        #[serde(default)]
+        basis: RecipeBasis,
+    },
+}
+
+/// What amounts of a product made by a sub-recipe measure.
+///
+/// Facts of the sub-recipe are per 100g of its finished dish either way, so
+/// the parent's facts per 100g only differ in how much of the dish an
+/// ingredient adds: by weight, or by whole dishes weighing what the finished
+/// sub-recipe does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RecipeBasis {
+    /// Amounts are weighed like those of any product.
+    #[default]
+    HundredGrams,
+    /// Amounts without a unit or in pieces count whole finished dishes, other
+    /// units weigh them. Such amounts can't be weighed before the sub-recipe
+    /// is computed, so scaling to a weight, serving weights and costs fail
+    /// with `UnknownRecipeWeight`.
+    Whole,
+}
+
+/// Share of another product in a blend.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+pub struct BlendComponent {
+    pub product: String,
+    pub percent: f64,
+}
+
+impl BlendComponent {
+    pub fn new(product: impl Into<String>, percent: f64) -> Self {
+        Self {
+            product: product.into(),
+            percent,
+        }
+    }
+}
+
+/// Ordered like [`Nutrition::KNOWN`], then custom nutrients by name.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone)]
+pub enum Nutrition {
+    Energy,
+    Proteins,
+    Fats,
+    Carbohydrates,
+    Fiber,
+    Sugars,
+    Sodium,
+    Water,
+    /// Any nutrient the crate doesn't know about, keyed by its name.
+    Other(String),
+}
+
+impl Nutrition {
+    /// Every nutrient except `Other`, in display order. Variants are declared
+    /// in the same order.
+    pub const KNOWN: [Nutrition; 8] = [
+        Nutrition::Energy,
+        Nutrition::Proteins,
+        Nutrition::Fats,
+        Nutrition::Carbohydrates,
+        Nutrition::Fiber,
+        Nutrition::Sugars,
+        Nutrition::Sodium,
+        Nutrition::Water,
+    ];
+
+    pub fn from_name(name: &str) -> Self {
+        Self::KNOWN
+            .iter()
+            .find(|item| item.name() == name)
+            .cloned()
+            .unwrap_or_else(|| Nutrition::Other(name.into()))
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Nutrition::Energy => "Energy",
+            Nutrition::Proteins => "Proteins",
+            Nutrition::Fats => "Fats",
+            Nutrition::Carbohydrates => "Carbohydrates",
+            Nutrition::Fiber => "Fiber",
+            Nutrition::Sugars => "Sugars",
+            Nutrition::Sodium => "Sodium",
+            Nutrition::Water => "Water",
+            Nutrition::Other(name) => name,
+        }
+    }
+
+    /// Unit the nutrient is measured in per 100g, empty for custom nutrients.
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Nutrition::Energy => "kcal",
+            Nutrition::Proteins
+            | Nutrition::Fats
+            | Nutrition::Carbohydrates
+            | Nutrition::Fiber
+            | Nutrition::Sugars
+            | Nutrition::Water => "g",
+            Nutrition::Sodium => "mg",
+            Nutrition::Other(_) => "",
+        }
+    }
+
+    /// Section of labels and tables the nutrient is listed in. Custom
+    /// nutrients are taken for vitamins and minerals.
+    pub fn group(&self) -> NutrientGroup {
+        match self {
+            Nutrition::Energy
+            | Nutrition::Proteins
+            | Nutrition::Fats
+            | Nutrition::Carbohydrates
+            | Nutrition::Fiber
+            | Nutrition::Sugars
+            | Nutrition::Water => NutrientGroup::Macronutrients,
+            Nutrition::Sodium | Nutrition::Other(_) => NutrientGroup::Micronutrients,
+        }
+    }
+}
+
+/// Nutrients weighed in grams and those, like minerals and vitamins, present
+/// in far smaller amounts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NutrientGroup {
+    Macronutrients,
+    Micronutrients,
+}
+
+impl NutrientGroup {
+    /// Every group, in the order sections are listed.
+    pub const ALL: [NutrientGroup; 2] =
+        [NutrientGroup::Macronutrients, NutrientGroup::Micronutrients];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            NutrientGroup::Macronutrients => "Macronutrients",
+            NutrientGroup::Micronutrients => "Micronutrients",
+        }
+    }
+}
+
+impl fmt::Display for Nutrition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for Nutrition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Nutrition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Nutrition::from_name(&name))
+    }
+}
+
+/// Serialization format recipes and product libraries are written in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputFormat {
+    Yaml,
+    Json,
+}
+
+impl InputFormat {
+    /// JSON for `.json` files, YAML for any other, gzipped files being told
+    /// by the extension before `.gz`.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = if is_gzipped(path) {
+            path.file_stem().map(Path::new).and_then(Path::extension)
+        } else {
+            path.extension()
+        };
+        match extension {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => InputFormat::Json,
+            _ => InputFormat::Yaml,
+        }
+    }
+
+    fn parse<T: DeserializeOwned>(self, contents: &str) -> Result<T, RecipeError> {
+        Ok(match self {
+            InputFormat::Yaml => serde_yaml::from_str(contents)?,
+            InputFormat::Json => serde_json::from_str(contents)?,
+        })
+    }
+
+    /// Parses a recipe or a library as `options` tell.
+    fn parse_with<T: DeserializeOwned>(
+        self,
+        contents: &str,
+        options: ParseOptions,
+    ) -> Result<T, RecipeError> {
+        range::with_decimal_comma(options.decimal_comma, || self.parse(contents))
+    }
+}
+
+/// Environment variable listing directories searched for recipe and library
+/// files that aren't found next to the recipe referencing them.
+pub const SEARCH_PATH_VAR: &str = "NUTRITIONS_PATH";
+
+/// Canonical path of the file `path` refers to. A relative path is looked up
+/// in `base`, or the current directory, then in every directory listed in
+/// [`SEARCH_PATH_VAR`], failing with `FileNotFound` when it's in none.
+pub(crate) async fn locate(path: &Path, base: Option<&Path>) -> Result<PathBuf, RecipeError> {
+    locate_in(path, base, std::env::var_os(SEARCH_PATH_VAR).as_deref()).await
+}
+
+/// Canonical path of the file `path` refers to, like [`locate`] with the
+/// directories of `search_path` instead of those of [`SEARCH_PATH_VAR`].
+async fn locate_in(
+    path: &Path,
+    base: Option<&Path>,
+    search_path: Option<&OsStr>,
+) -> Result<PathBuf, RecipeError> {
+    let mut searched = vec![match base {
+        Some(base) => base.join(path),
+        None => path.to_path_buf(),
+    }];
+    if path.is_relative() {
+        if let Some(dirs) = search_path {
+            searched.extend(
+                std::env::split_paths(dirs)
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .map(|dir| dir.join(path)),
+            );
+        }
+    }
+    for candidate in &searched {
+        match tokio::fs::canonicalize(candidate).await {
+            Ok(canonical) => return Ok(canonical),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Err(RecipeError::FileNotFound {
+        path: path.to_path_buf(),
+        searched,
+    })
+}
+
+/// Environment variable naming the directory the default product database
+/// is read from instead of `~/.config/nutritions`.
+pub const CONFIG_DIR_VAR: &str = "NUTRITIONS_CONFIG_DIR";
+
+/// Path of the products file shared by every recipe, `products.yaml` in the
+/// directory of [`CONFIG_DIR_VAR`] or in `~/.config/nutritions`. `None` when
+/// neither that variable nor `HOME` is set.
+pub fn default_database_path() -> Option<PathBuf> {
+    database_path(std::env::var_os(CONFIG_DIR_VAR), std::env::var_os("HOME"))
+}
+
+/// Path of the default products file given the config directory and the
+/// home directory, like [`default_database_path`].
+fn database_path(config_dir: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+    let dir = match config_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(home?).join(".config/nutritions"),
+    };
+    Some(dir.join("products.yaml"))
+}
+
+impl Recipe {
+    /// Reads a recipe in the format told by the file extension. A relative
+    /// path is looked up in the current directory, then in the directories
+    /// of [`SEARCH_PATH_VAR`].
+    pub async fn read_from_file(path: &Path) -> Result<Self, RecipeError> {
+        Recipe::read_from_file_as(path, InputFormat::from_path(path)).await
+    }
+
+    /// Reads a recipe in the given format whatever the file extension.
+    pub async fn read_from_file_as(path: &Path, format: InputFormat) -> Result<Self, RecipeError> {
+        Recipe::load(path, format, None, ParseOptions::default()).await
+    }
+
+    /// Reads a recipe like [`Recipe::read_from_file_as`], adding the time
+    /// spent to `timings`.
+    pub async fn read_from_file_timed(
+        path: &Path,
+        format: InputFormat,
+        timings: &Timings,
+    ) -> Result<Self, RecipeError> {
+        Recipe::load(path, format, Some(timings), ParseOptions::default()).await
+    }
+
+    /// Reads a recipe like [`Recipe::read_from_file_timed`], parsing it and
+    /// its libraries as `options` tell.
+    pub async fn read_from_file_with(
+        path: &Path,
+        format: InputFormat,
+        timings: &Timings,
+        options: ParseOptions,
+    ) -> Result<Self, RecipeError> {
+        Recipe::load(path, format, Some(timings), options).await
+    }
+
+    pub(crate) async fn load(
+        path: &Path,
+        format: InputFormat,
+        timings: Option<&Timings>,
+        options: ParseOptions,
+    ) -> Result<Self, RecipeError> {
+        let start = Instant::now();
+        let canonical = locate(path, None).await?;
+        let file_contents = read_text(&canonical).await?;
+        if let Some(timings) = timings {
+            timings.count_file();
+            timings.add_io(start);
+        }
+        let start = Instant::now();
+        let mut recipe = Recipe::from_str_with(&file_contents, format, options)
+            .map_err(|err| err.in_file(path))?;
+        if let Some(timings) = timings {
+            timings.add_parsing(start);
+        }
+        recipe.path = Some(canonical);
+        recipe.include_libraries(timings, options).await
+    }
+
+    /// Parses a recipe from YAML without touching the filesystem.
+    ///
+    /// Sub-recipes referenced by products are only loaded when facts are
+    /// computed, so a recipe without such references needs no async runtime.
+    /// Included product libraries are left for [`Recipe::resolve_includes`].
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, RecipeError> {
+        Recipe::from_str_as(yaml, InputFormat::Yaml)
+    }
+
+    /// Parses a recipe from JSON, like [`Recipe::from_yaml_str`].
+    pub fn from_json_str(json: &str) -> Result<Self, RecipeError> {
+        Recipe::from_str_as(json, InputFormat::Json)
+    }
+
+    /// Parses a recipe in the given format, like [`Recipe::from_yaml_str`].
+    pub fn from_str_as(contents: &str, format: InputFormat) -> Result<Self, RecipeError> {
+        Recipe::from_str_with(contents, format, ParseOptions::default())
+    }
+
+    /// Parses a recipe like [`Recipe::from_str_as`], failing with
+    /// `UnknownFields` for fields no recipe has instead of ignoring them, so
+    /// that misspelled ones like `wieght` are caught.
+    pub fn from_str_strict(contents: &str, format: InputFormat) -> Result<Self, RecipeError> {
+        let options = ParseOptions {
+            strict: true,
+            ..ParseOptions::default()
+        };
+        Recipe::from_str_with(contents, format, options)
+    }
+
+    /// Parses a recipe like [`Recipe::from_str_as`] as `options` tell.
+    pub fn from_str_with(
+        contents: &str,
+        format: InputFormat,
+        options: ParseOptions,
+    ) -> Result<Self, RecipeError> {
+        let mut recipe: Recipe = format.parse_with(contents, options)?;
+        if options.no_files {
+            if let Some(path) = recipe.referenced_files().next() {
+                return Err(RecipeError::FileReference { path: path.into() });
+            }
+        }
+        recipe.validate()?;
+        recipe.normalize_bases()?;
+        if options.strict {
+            let fields = schema::unknown_fields(&format.parse_with(contents, options)?);
+            if !fields.is_empty() {
+                return Err(RecipeError::UnknownFields { fields });
+            }
+        }
+        Ok(recipe)
+    }
+
+    /// Merges products of the included libraries into the recipe's own.
+    /// Relative paths are looked up as by [`Recipe::read_from_file`] from the
+    /// directory of the recipe file, and libraries are parsed by their
+    /// extension. Fails with `DuplicateProduct` when a name is defined both
+    /// in the recipe and a library or in several libraries.
+    ///
+    /// With [`ParseOptions::default_database`], products of the default
+    /// database are merged afterwards, except those named like a product or
+    /// an alias the recipe already has.
+    pub async fn resolve_includes(self) -> Result<Self, RecipeError> {
+        self.include_libraries(None, ParseOptions::default()).await
+    }
+
+    /// Merges products of the included libraries like
+    /// [`Recipe::resolve_includes`], parsing them as `options` tell.
+    pub async fn resolve_includes_with(self, options: ParseOptions) -> Result<Self, RecipeError> {
+        self.include_libraries(None, options).await
+    }
+
+    async fn include_libraries(
+        mut self,
+        timings: Option<&Timings>,
+        options: ParseOptions,
+    ) -> Result<Self, RecipeError> {
+        let base = self.path.as_deref().and_then(Path::parent);
+        let start = Instant::now();
+        let includes = std::mem::take(&mut self.include);
+        let paths = try_join_all(includes.iter().map(|path| locate(path, base))).await?;
+        let libraries = try_join_all(paths.iter().map(|path| read_text(path))).await?;
+        if let Some(timings) = timings {
+            timings.add_io(start);
+        }
+        let start = Instant::now();
+        for (path, library) in paths.iter().zip(libraries) {
+            let library: ProductLibrary = InputFormat::from_path(path)
+                .parse_with(&library, options)
+                .map_err(|err| err.in_file(path))?;
+            self.products.extend(library.products);
+        }
+        self.libraries.extend(paths);
+        if let Some(path) = default_database_path().filter(|_| options.default_database) {
+            self.include_database(path, options).await?;
+        }
+        self.validate()?;
+        self.normalize_bases()?;
+        if let Some(timings) = timings {
+            timings.add_parsing(start);
+        }
+        Ok(self)
+    }
+
+    /// Adds the products of the database at `path` the recipe uses without
+    /// defining them, doing nothing when there's no such file. Products the
+    /// recipe doesn't use are left out, so they're neither validated nor
+    /// listed along the recipe's own.
+    async fn include_database(
+        &mut self,
+        path: PathBuf,
+        options: ParseOptions,
+    ) -> Result<(), RecipeError> {
+        let database = match read_text(&path).await {
+            Ok(database) => database,
+            Err(RecipeError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {
+                debug!("No default product database at {}", path.display());
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        let database: ProductLibrary = InputFormat::from_path(&path)
+            .parse_with(&database, options)
+            .map_err(|err| err.in_file(&path))?;
+        let mut products = database.products;
+        while let Some(i) = products
+            .iter()
+            .position(|product| self.undefined_names().any(|name| product.is_named(name)))
+        {
+            self.products.push(products.swap_remove(i));
+        }
+        self.libraries.push(path);
+        Ok(())
+    }
+
+    /// Names of the products ingredients and blends use that the recipe
+    /// doesn't have, aliases being resolved.
+    fn undefined_names(&self) -> impl Iterator<Item = &str> {
+        let ingredients = self
+            .dishes()
+            .into_iter()
+            .flat_map(|dish| dish.ingredients.iter())
+            .map(|ingredient| ingredient.product.as_str());
+        let components = self
+            .products
+            .iter()
+            .flat_map(|product| match &product.nutrition_data {
+                NutritionData::Blend(components) => components.as_slice(),
+                _ => &[],
+            })
+            .map(|component| component.product.as_str());
+        ingredients
+            .chain(components)
+            .map(|name| self.product_name(name))
+            .filter(|name| !self.products.iter().any(|product| product.is_named(name)))
+    }
+
+    /// Paths of the libraries and sub-recipes as the recipe writes them.
+    fn referenced_files(&self) -> impl Iterator<Item = &Path> {
+        let sub_recipes =
+            self.products
+                .iter()
+                .filter_map(|product| match &product.nutrition_data {
+                    NutritionData::Recipe(path, _) => Some(path.as_path()),
+                    _ => None,
+                });
+        self.include.iter().map(PathBuf::as_path).chain(sub_recipes)
+    }
+
+    /// Canonical paths of the files the recipe is read from: its own, the
+    /// libraries it includes and its sub-recipes, recursively. Facts only
+    /// change along with one of them.
+    ///
+    /// Sub-recipes that fail to load are listed without the files they'd
+    /// be read from, and missing ones at the path next to the recipe they're
+    /// expected at, so that fixing or creating them changes the facts too.
+    pub async fn files(&self) -> Result<Vec<PathBuf>, RecipeError> {
+        self.files_with(ParseOptions::default()).await
+    }
+
+    /// Files the recipe is read from like [`Recipe::files`], sub-recipes
+    /// being read as `options` tell.
+    pub async fn files_with(&self, options: ParseOptions) -> Result<Vec<PathBuf>, RecipeError> {
+        let mut files = Vec::new();
+        self.collect_files(options, &mut files).await?;
+        Ok(files)
+    }
+
+    #[async_recursion]
+    async fn collect_files(
+        &self,
+        options: ParseOptions,
+        files: &mut Vec<PathBuf>,
+    ) -> Result<(), RecipeError> {
+        files.extend(self.path.iter().cloned());
+        files.extend(self.libraries.iter().cloned());
+        let base = self.path.as_deref().and_then(Path::parent);
+        for product in &self.products {
+            let NutritionData::Recipe(ref path, _) = product.nutrition_data else {
+                continue;
+            };
+            let path = match locate(path, base).await {
+                Ok(path) => path,
+                Err(RecipeError::FileNotFound { .. }) => {
+                    files.extend(base.map(|base| base.join(path)));
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            if files.contains(&path) {
+                continue;
+            }
+            match Recipe::load(&path, InputFormat::from_path(&path), None, options).await {
+                Ok(recipe) => recipe.collect_files(options, files).await?,
+                Err(err) => {
+                    debug!(
+                        "Listing {} alone as it fails to load: {}",
+                        path.display(),
+                        err
+                    );
+                    files.push(path);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rejects values no real recipe can have: products sharing a name,
+    /// negative or non-finite nutrients and ingredient amounts, facts per a
+    /// basis that can't be weighed, and a non-positive dish weight or number
+    /// of servings.
+    pub fn validate(&self) -> Result<(), RecipeError> {
+        for (i, product) in self.products.iter().enumerate() {
+            if self.products[..i].iter().any(|p| p.is_named(&product.name)) {
+                return Err(RecipeError::DuplicateProduct {
+                    name: product.name.clone(),
+                });
+            }
+        }
+        for product in &self.products {
+            if let Some(density) = product.density_g_per_ml {
+                check(
+                    || format!("products[{}].density_g_per_ml", product.name),
+                    density,
+                    |density| density > 0.0,
+                )?;
+            }
+            if product.per.is_some() && product.basis.is_some() {
+                return Err(RecipeError::ConflictingBasis {
+                    product: product.name.clone(),
+                });
+            }
+            if let Some(ref basis) = product.per {
+                check(
+                    || format!("products[{}].per.amount", product.name),
+                    basis.amount,
+                    |amount| amount > 0.0,
+                )?;
+            }
+            if let Some(grams) = product.grams_per_piece {
+                check(
+                    || format!("products[{}].grams_per_piece", product.name),
+                    grams,
+                    |grams| grams > 0.0,
+                )?;
+            }
+            if let Some(grams) = product.serving_weight_g {
+                check(
+                    || format!("products[{}].serving_weight_g", product.name),
+                    grams,
+                    |grams| grams > 0.0,
+                )?;
+            }
+            if let Some(scale) = product.scale {
+                check(
+                    || format!("products[{}].scale", product.name),
+                    scale,
+                    |scale| scale > 0.0,
+                )?;
+            }
+            // Facts per a basis that can't be weighed can never be used.
+            product.to_hundred_grams()?;
+            if let Some(price) = product.price_per_100g {
+                check(
+                    || format!("products[{}].price_per_100g", product.name),
+                    price,
+                    |price| price >= 0.0,
+                )?;
+            }
+            match product.nutrition_data {
+                NutritionData::Facts(ref facts) => {
+                    for (nutrient, value) in &facts.0 {
+                        check(
+                            || format!("products[{}].{}", product.name, nutrient),
+                            *value,
+                            |value| value >= 0.0,
+                        )?;
+                    }
+                }
+                NutritionData::Range(ref range) => {
+                    for (nutrient, min) in &range.min.0 {
+                        let max = range.max.0[nutrient];
+                        check(
+                            || format!("products[{}].{}.min", product.name, nutrient),
+                            *min,
+                            |min| min >= 0.0,
+                        )?;
+                        check(
+                            || format!("products[{}].{}.max", product.name, nutrient),
+                            max,
+                            |max| max >= *min,
+                        )?;
+                    }
+                }
+                NutritionData::Blend(ref components) => {
+                    for component in components {
+                        check(
+                            || {
+                                format!(
+                                    "products[{}].blend[{}].percent",
+                                    product.name, component.product
+                                )
+                            },
+                            component.percent,
+                            |percent| percent >= 0.0,
+                        )?;
+                    }
+                    let total: f64 = components.iter().map(|c| c.percent).sum();
+                    if (total - 100.0).abs() > BLEND_TOLERANCE {
+                        return Err(RecipeError::BlendPercentage {
+                            product: product.name.clone(),
+                            total,
+                        });
+                    }
+                }
+                NutritionData::Recipe(..) => {}
+            }
+        }
+        for (field, dish) in self.labelled_dishes() {
+            for ingredient in &dish.ingredients {
+                check(
+                    || format!("{}.ingredients[{}].amount", field, ingredient.product),
+                    ingredient.amount,
+                    |amount| amount >= 0.0 && !(ingredient.to_taste && amount > 0.0),
+                )?;
+            }
+            if let Some(weight) = dish.weight {
+                check(
+                    || format!("{}.weight", field),
+                    weight,
+                    |weight| weight > 0.0,
+                )?;
+            }
+            if let Some(weight) = dish.cooked_weight {
+                check(
+                    || format!("{}.cooked_weight", field),
+                    weight,
+                    |weight| weight > 0.0,
+                )?;
+            }
+            if let Some(servings) = dish.servings {
+                check(
+                    || format!("{}.servings", field),
+                    servings,
+                    |servings| servings > 0.0,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts facts of every product to facts per 100g, once the products
+    /// are validated.
+    fn normalize_bases(&mut self) -> Result<(), RecipeError> {
+        self.products
+            .iter_mut()
+            .try_for_each(Product::normalize_basis)
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Named components of a composed dish, each as a recipe of its own
+    /// sharing the products. Empty for a recipe with a single dish.
+    pub fn components(&self) -> Vec<(&str, Recipe)> {
+        match &self.dishes {
+            Dishes::Dish(_) => Vec::new(),
+            Dishes::Dishes(components) => components
+                .iter()
+                .map(|component| {
+                    let recipe = Recipe {
+                        include: Vec::new(),
+                        products: self.products.clone(),
+                        aliases: self.aliases.clone(),
+                        dishes: Dishes::Dish(component.dish.clone()),
+                        metadata: self.metadata.clone(),
+                        path: self.path.clone(),
+                        libraries: self.libraries.clone(),
+                    };
+                    (component.name.as_str(), recipe)
+                })
+                .collect(),
+        }
+    }
+
+    fn dishes(&self) -> Vec<&Dish> {
+        match &self.dishes {
+            Dishes::Dish(dish) => vec![dish],
+            Dishes::Dishes(components) => components.iter().map(|c| &c.dish).collect(),
+        }
+    }
+
+    fn dishes_mut(&mut self) -> Vec<&mut Dish> {
+        match &mut self.dishes {
+            Dishes::Dish(dish) => vec![dish],
+            Dishes::Dishes(components) => components.iter_mut().map(|c| &mut c.dish).collect(),
+        }
+    }
+
+    /// Dishes with the name of the field they are declared in.
+    fn labelled_dishes(&self) -> Vec<(String, &Dish)> {
+        match &self.dishes {
+            Dishes::Dish(dish) => vec![("dish".into(), dish)],
+            Dishes::Dishes(components) => components
+                .iter()
+                .map(|c| (format!("dishes[{}]", c.name), &c.dish))
+                .collect(),
+        }
+    }
+
+    /// Names of products no ingredient of the dish refers to.
+    pub fn unused_products(&self) -> Vec<&str> {
+        let dishes = self.dishes();
+        self.products
+            .iter()
+            .filter(|product| {
+                !dishes
+                    .iter()
+                    .flat_map(|dish| &dish.ingredients)
+                    .any(|ingredient| product.is_named(self.product_name(&ingredient.product)))
+                    && !self.blend_components().any(|name| product.is_named(name))
+            })
+            .map(|product| product.name.as_str())
+            .collect()
+    }
+
+    /// Names of the products blends are made of.
+    fn blend_components(&self) -> impl Iterator<Item = &str> {
+        self.products
+            .iter()
+            .filter_map(|product| match &product.nutrition_data {
+                NutritionData::Blend(components) => Some(components),
+                _ => None,
+            })
+            .flatten()
+            .map(|component| self.product_name(&component.product))
+    }
+
+    /// Products whose declared energy differs from the one expected from
+    /// their macronutrients by more than [`ENERGY_DISCREPANCY_THRESHOLD`],
+    /// with the relative discrepancy.
+    pub fn energy_discrepancies(&self) -> Vec<(&str, f64)> {
+        self.products
+            .iter()
+            .filter_map(|product| {
+                product
+                    .nutrition_data
+                    .nominal_facts()?
+                    .energy_discrepancy()
+                    .filter(|discrepancy| *discrepancy > ENERGY_DISCREPANCY_THRESHOLD)
+                    .map(|discrepancy| (product.name.as_str(), discrepancy))
+            })
+            .collect()
+    }
+
+    /// Products whose macronutrients and water weigh more than 100g per 100g,
+    /// with the grams they add up to. Products whose basis can't be
+    /// converted to grams are skipped.
+    pub fn overweight_products(&self) -> Vec<(&str, f64)> {
+        self.products
+            .iter()
+            .filter_map(|product| {
+                let facts = product.nutrition_data.nominal_facts()?;
+                product
+                    .to_hundred_grams()
+                    .ok()
+                    .map(|factor| facts.mass() * factor)
+                    .filter(|grams| *grams > 100.0)
+                    .map(|grams| (product.name.as_str(), grams))
+            })
+            .collect()
+    }
+
+    /// Ingredients weighing more than [`OVERWEIGHT_INGREDIENT_RATIO`] times
+    /// the declared weight of their dish, with the grams they weigh and the
+    /// dish weight. Such a dish weight was likely entered in the wrong unit.
+    /// Ingredients that can't be weighed are skipped.
+    pub fn overweight_ingredients(&self) -> Vec<(&str, f64, f64)> {
+        self.dishes()
+            .into_iter()
+            .flat_map(|dish| self.overweight_in(dish))
+            .collect()
+    }
+
+    fn overweight_in<'a>(&'a self, dish: &'a Dish) -> Vec<(&'a str, f64, f64)> {
+        let Some(weight) = dish.weight else {
+            return Vec::new();
+        };
+        dish.ingredients
+            .iter()
+            .filter_map(|ingredient| {
+                let product = self.find_product(&ingredient.product).ok()?;
+                let grams = ingredient.grams(product, dish.weight).ok()?;
+                (grams > OVERWEIGHT_INGREDIENT_RATIO * weight).then_some((
+                    ingredient.product.as_str(),
+                    grams,
+                    weight,
+                ))
+            })
+            .collect()
+    }
+
+    /// Recipe with the ingredients of both recipes and the union of their
+    /// products, fails with `DuplicateProduct` when both define a product of
+    /// the same name differently. Aliases of `self` win over the ones of
+    /// `other`.
+    ///
+    /// Two single dishes become one whose weights are the sums of both when
+    /// both declare them and whose servings are the ones of `self`. Otherwise
+    /// components of both are kept, a single dish becoming a component named
+    /// after its file. Relative paths of `other` keep pointing to the same
+    /// files.
+    pub fn merged(&self, other: &Recipe) -> Result<Recipe, RecipeError> {
+        let other = other.rebased(self.path.as_deref().and_then(Path::parent));
+        let mut recipe = self.clone();
+        for product in other.products {
+            match recipe.products.iter().find(|p| p.is_named(&product.name)) {
+                Some(existing) if existing.same_as(&product) => {}
+                Some(_) => return Err(RecipeError::DuplicateProduct { name: product.name }),
+                None => recipe.products.push(product),
+            }
+        }
+        recipe.include.extend(other.include);
+        recipe.libraries.extend(other.libraries);
+        for (alias, name) in other.aliases {
+            recipe.aliases.entry(alias).or_insert(name);
+        }
+        recipe.dishes = match (recipe.dishes, other.dishes) {
+            (Dishes::Dish(mut dish), Dishes::Dish(other_dish)) => {
+                dish.ingredients.extend(other_dish.ingredients);
+                dish.weight = dish.weight.zip(other_dish.weight).map(|(a, b)| a + b);
+                dish.cooked_weight = dish
+                    .cooked_weight
+                    .zip(other_dish.cooked_weight)
+                    .map(|(a, b)| a + b);
+                Dishes::Dish(dish)
+            }
+            (dishes, other_dishes) => Dishes::Dishes(
+                dishes
+                    .into_components(self.path.as_deref())
+                    .chain(other_dishes.into_components(other.path.as_deref()))
+                    .collect(),
+            ),
+        };
+        recipe.validate()?;
+        Ok(recipe)
+    }
+
+    /// Same recipe with relative paths of included libraries and sub-recipes
+    /// made relative to `base` instead of the directory of the recipe file.
+    fn rebased(&self, base: Option<&Path>) -> Recipe {
+        let mut recipe = self.clone();
+        let Some(dir) = self.path.as_deref().and_then(Path::parent) else {
+            return recipe;
+        };
+        if Some(dir) == base {
+            return recipe;
+        }
+        let rebase = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = dir.join(&*path);
+            }
+        };
+        recipe.include.iter_mut().for_each(rebase);
+        for product in &mut recipe.products {
+            if let NutritionData::Recipe(ref mut path, _) = product.nutrition_data {
+                rebase(path);
+            }
+        }
+        recipe
+    }
+
+    /// Same recipe with every ingredient amount but percentages, the dish
+    /// weights and the number of servings multiplied by `factor`.
+    pub fn scaled(&self, factor: f64) -> Recipe {
+        let mut recipe = self.clone();
+        for dish in recipe.dishes_mut() {
+            // Percentages of the dish grow with its weight.
+            for ingredient in &mut dish.ingredients {
+                if !matches!(ingredient.parsed_unit(), Ok(Unit::Percent)) {
+                    ingredient.amount *= factor;
+                }
+            }
+            dish.weight = dish.weight.map(|weight| weight * factor);
+            dish.cooked_weight = dish.cooked_weight.map(|weight| weight * factor);
+            dish.servings = dish.servings.map(|servings| servings * factor);
+        }
+        recipe
+    }
+
+    /// Same recipe weighing `weight` grams once cooked, whatever weights the
+    /// dishes declare. Weights of composed dishes are scaled in proportion so
+    /// that they add up to `weight`.
+    pub fn with_weight(&self, weight: f64) -> Result<Recipe, RecipeError> {
+        check(|| "weight".into(), weight, |weight| weight > 0.0)?;
+        let weights = self
+            .dishes()
+            .into_iter()
+            .map(|dish| Ok(dish.final_weight(self.raw_weight(dish)?)))
+            .collect::<Result<Vec<f64>, RecipeError>>()?;
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return Err(RecipeError::ZeroWeight);
+        }
+        let mut recipe = self.clone();
+        for (dish, dish_weight) in recipe.dishes_mut().into_iter().zip(weights) {
+            dish.weight = Some(dish_weight / total * weight);
+            dish.cooked_weight = None;
+        }
+        Ok(recipe)
+    }
+
+    /// Same recipe with ingredient amounts taken as proportions and scaled so
+    /// that the raw ingredients weigh `weight` grams together, as by
+    /// [`Recipe::scaled`].
+    pub fn scaled_to(&self, weight: f64) -> Result<Recipe, RecipeError> {
+        check(|| "target_weight".into(), weight, |weight| weight > 0.0)?;
+        let mut total = 0.0;
+        for dish in self.dishes() {
+            total += self.raw_weight(dish)?;
+        }
+        if total <= 0.0 {
+            return Err(RecipeError::ZeroWeight);
+        }
+        Ok(self.scaled(weight / total))
+    }
+
+    /// Grams the raw ingredients of `dish` weigh.
+    fn raw_weight(&self, dish: &Dish) -> Result<f64, RecipeError> {
+        let mut raw = 0.0;
+        for ingredient in &dish.ingredients {
+            raw += ingredient.grams(self.find_product(&ingredient.product)?, dish.weight)?;
+        }
+        Ok(raw)
+    }
+
+    /// Allergens of every product used by an ingredient, including products of
+    /// sub-recipes, lowercased and sorted.
+    pub async fn allergens(&self) -> Result<Vec<String>, RecipeError> {
+        self.allergens_with(&ComputeOptions::default()).await
+    }
+
+    pub async fn allergens_with(
+        &self,
+        options: &ComputeOptions,
+    ) -> Result<Vec<String>, RecipeError> {
+        let resolution = Resolution::new(options, self.path.as_deref());
+        Ok(self.allergens_in(&resolution).await?.into_iter().collect())
+    }
+
+    #[async_recursion]
+    async fn allergens_in(
+        &self,
+        resolution: &Resolution<'_>,
+    ) -> Result<BTreeSet<String>, RecipeError> {
+        let base = self.path.as_deref().and_then(Path::parent);
+        let mut allergens = BTreeSet::new();
+        let mut paths = Vec::new();
+        for ingredient in self.dishes().into_iter().flat_map(|dish| &dish.ingredients) {
+            let Some(product) = self.used_product(ingredient, resolution.options)? else {
+                continue;
+            };
+            self.collect_allergens(product, base, &mut Vec::new(), &mut allergens, &mut paths)
+                .await?;
+        }
+        paths.sort();
+        paths.dedup();
+        let sub_allergens = try_join_all(paths.into_iter().map(|path| async move {
+            let resolution = resolution.enter(path.clone())?;
+            async {
+                let recipe = resolution.read(&path).await?;
+                recipe.allergens_in(&resolution).await
+            }
+            .await
+            .map_err(|err| resolution.wrap(err))
+        }))
+        .await?;
+        allergens.extend(sub_allergens.into_iter().flatten());
+        Ok(allergens)
+    }
+
+    /// Adds the allergens of `product` and of the products it's blended from,
+    /// collecting the paths of sub-recipes whose allergens are still to be
+    /// found. `blends` holds the blends `product` is a component of.
+    #[async_recursion]
+    async fn collect_allergens(
+        &self,
+        product: &Product,
+        base: Option<&'async_recursion Path>,
+        blends: &mut Vec<String>,
+        allergens: &mut BTreeSet<String>,
+        paths: &mut Vec<PathBuf>,
+    ) -> Result<(), RecipeError> {
+        allergens.extend(product.allergens.iter().map(|name| normalize_name(name)));
+        match product.canonical_nutrition_data(base).await? {
+            NutritionData::Facts(_) | NutritionData::Range(_) => {}
+            NutritionData::Recipe(path, _) => paths.push(path),
+            NutritionData::Blend(components) => {
+                enter_blend(blends, product)?;
+                for component in &components {
+                    let component = self.find_product(&component.product)?;
+                    self.collect_allergens(component, base, blends, allergens, paths)
+                        .await?;
+                }
+                blends.pop();
+            }
+        }
+        Ok(())
+    }
+
+    /// Price of the ingredients of every dish, fails with `MissingPrice` when
+    /// a used product has no price. Ingredients to taste or of no amount
+    /// cost nothing, whether their product has a price or not.
+    pub fn total_cost(&self) -> Result<Cost, RecipeError> {
+        self.total_cost_with(&ComputeOptions::default())
+    }
+
+    /// Price of the ingredients like [`Recipe::total_cost`], leaving out
+    /// ingredients whose product is missing when `options` allow it.
+    pub fn total_cost_with(&self, options: &ComputeOptions) -> Result<Cost, RecipeError> {
+        let mut total = 0.0;
+        let mut per_serving = Some(0.0);
+        for dish in self.dishes() {
+            let mut dish_cost = 0.0;
+            for ingredient in &dish.ingredients {
+                if ingredient.is_to_taste() || ingredient.amount == 0.0 {
+                    continue;
+                }
+                let Some(product) = self.used_product(ingredient, options)? else {
+                    continue;
+                };
+                let price = product
+                    .price_per_100g
+                    .ok_or_else(|| RecipeError::MissingPrice {
+                        product: product.name.clone(),
+                    })?;
+                dish_cost += price / 100.0 * ingredient.grams(product, dish.weight)?;
+            }
+            total += dish_cost;
+            per_serving = per_serving
+                .zip(dish.servings)
+                .map(|(cost, servings)| cost + dish_cost / servings);
+        }
+        Ok(Cost { total, per_serving })
+    }
+
+    pub async fn get_nutrition_facts(&self) -> Result<NutritionFacts, RecipeError> {
+        self.get_nutrition_facts_with(&ComputeOptions::default())
+            .await
+    }
+
+    pub async fn get_nutrition_facts_with(
+        &self,
+        options: &ComputeOptions,
+    ) -> Result<NutritionFacts, RecipeError> {
+        let resolution = Resolution::new(options, self.path.as_deref());
+        let facts = self.get_nutrition_facts_in(&resolution).await?;
+        Ok(facts.rounded(options.rounding))
+    }
+
+    /// Facts per 100g along with the raw and the finished weight of the
+    /// dishes.
+    pub async fn compute(&self) -> Result<ComputedRecipe, RecipeError> {
+        self.compute_with(&ComputeOptions::default()).await
+    }
+
+    pub async fn compute_with(
+        &self,
+        options: &ComputeOptions,
+    ) -> Result<ComputedRecipe, RecipeError> {
+        let resolution = Resolution::new(options, self.path.as_deref());
+        let computed = self.compute_in(&resolution).await?;
+        Ok(ComputedRecipe {
+            facts: computed.facts.rounded(options.rounding),
+            ..computed
+        })
+    }
+
+    pub async fn get_nutrition_facts_per_serving(&self) -> Result<NutritionFacts, RecipeError> {
+        self.get_nutrition_facts_per_serving_with(&ComputeOptions::default())
+            .await
+    }
+
+    pub async fn get_nutrition_facts_per_serving_with(
+        &self,
+        options: &ComputeOptions,
+    ) -> Result<NutritionFacts, RecipeError> {
+        let dishes = self.dishes();
+        let servings = dishes
+            .iter()
+            .map(|dish| dish.servings.ok_or(RecipeError::MissingServings))
+            .collect::<Result<Vec<_>, _>>()?;
+        let resolution = Resolution::new(options, self.path.as_deref());
+        let totals =
+            try_join_all(dishes.iter().map(|dish| self.aggregate(dish, &resolution))).await?;
+
+        // A serving of a composed dish is a serving of every component.
+        Ok(totals
+            .into_iter()
+            .zip(servings)
+            .map(|((counted, _), servings)| {
+                NutritionFacts(
+                    counted
+                        .total_nutrition()
+                        .0
+                        .iter()
+                        .map(|(k, a)| (k.clone(), a / servings))
+                        .collect(),
+                )
+            })
+            .fold(NutritionFacts::default(), |total, facts| total + facts)
+            .rounded(options.rounding))
+    }
+
+    /// Grams a serving weighs, a serving of a composed dish taking one of
+    /// every component. Fails with `MissingServings` like
+    /// [`Recipe::get_nutrition_facts_per_serving`].
+    pub fn serving_weight(&self) -> Result<f64, RecipeError> {
+        let mut weight = 0.0;
+        for dish in self.dishes() {
+            let servings = dish.servings.ok_or(RecipeError::MissingServings)?;
+            weight += dish.final_weight(self.raw_weight(dish)?) / servings;
+        }
+        Ok(weight)
+    }
+
+    /// Nutrients of the whole dish, not normalized to any weight.
+    pub async fn get_total_nutrition(&self) -> Result<NutritionFacts, RecipeError> {
+        self.get_total_nutrition_with(&ComputeOptions::default())
+            .await
+    }
+
+    pub async fn get_total_nutrition_with(
+        &self,
+        options: &ComputeOptions,
+    ) -> Result<NutritionFacts, RecipeError> {
+        let resolution = Resolution::new(options, self.path.as_deref());
+        let dishes = self.dishes();
+        let totals =
+            try_join_all(dishes.iter().map(|dish| self.aggregate(dish, &resolution))).await?;
+        Ok(totals
+            .into_iter()
+            .map(|(counted, _)| counted.total_nutrition().clone())
+            .fold(NutritionFacts::default(), |total, facts| total + facts)
+            .rounded(options.rounding))
+    }
+
+    /// Nutrients of a portion of `grams` of the finished dish, whatever the
+    /// dish weighs.
+    pub async fn get_nutrition_facts_for_portion(
+        &self,
+        grams: f64,
+    ) -> Result<NutritionFacts, RecipeError> {
+        self.get_nutrition_facts_for_portion_with(grams, &ComputeOptions::default())
+            .await
+    }
+
+    pub async fn get_nutrition_facts_for_portion_with(
+        &self,
+        grams: f64,
+        options: &ComputeOptions,
+    ) -> Result<NutritionFacts, RecipeError> {
+        check(|| "portion".into(), grams, |grams| grams > 0.0)?;
+        let resolution = Resolution::new(options, self.path.as_deref());
+        let facts = self.get_nutrition_facts_in(&resolution).await?;
+        Ok((facts * (grams / 100.0)).rounded(options.rounding))
+    }
+
+    /// Facts per 100g of every product in the order they're defined, used or
+    /// not, sub-recipes and blends computed. Errors are wrapped in
+    /// `InProduct` naming the product they came from.
+    pub async fn resolved_products(&self) -> Result<Vec<(String, NutritionFacts)>, RecipeError> {
+        self.resolved_products_with(&ComputeOptions::default())
+            .await
+    }
+
+    pub async fn resolved_products_with(
+        &self,
+        options: &ComputeOptions,
+    ) -> Result<Vec<(String, NutritionFacts)>, RecipeError> {
+        let resolution = Resolution::new(options, self.path.as_deref());
+        let base = self.path.as_deref().and_then(Path::parent);
+        let mut products = Vec::with_capacity(self.products.len());
+        for product in &self.products {
+            let mut blends = vec![product.name.clone()];
+            let facts = self
+                .product_facts_in(product, base, &mut blends, &resolution)
+                .await
+                .map_err(|err| RecipeError::InProduct {
+                    product: product.name.clone(),
+                    source: Box::new(err),
+                })?;
+            products.push((product.name.clone(), facts.rounded(options.rounding)));
+        }
+        Ok(products)
+    }
+
+    /// Nutrients every ingredient contributes to the whole recipe by its
+    /// amount, named by the product the ingredient refers to. Ingredients of
+    /// composed dishes follow one dish after another.
+    pub async fn breakdown(&self) -> Result<Vec<(String, NutritionFacts)>, RecipeError> {
+        self.breakdown_with(&ComputeOptions::default()).await
+    }
+
+    pub async fn breakdown_with(
+        &self,
+        options: &ComputeOptions,
+    ) -> Result<Vec<(String, NutritionFacts)>, RecipeError> {
+        let resolution = Resolution::new(options, self.path.as_deref());
+        let dishes = self.dishes();
+        let contributions = try_join_all(
+            dishes
+                .iter()
+                .map(|dish| self.contributions(dish, &resolution)),
+        )
+        .await?;
+        Ok(contributions
+            .into_iter()
+            .flatten()
+            .map(|(ingredient, facts, _)| (ingredient.product.clone(), facts))
+            .collect())
+    }
+
+    /// Steps of computing the facts per 100g, for showing the work. The
+    /// facts of sub-recipes are computed once for the steps and the result.
+    pub async fn explain(&self) -> Result<Explanation, RecipeError> {
+        self.explain_with(&ComputeOptions::default()).await
+    }
+
+    pub async fn explain_with(&self, options: &ComputeOptions) -> Result<Explanation, RecipeError> {
+        let resolution = Resolution::new(options, self.path.as_deref());
+        let dishes = self.dishes();
+        let contributions = try_join_all(
+            dishes
+                .iter()
+                .map(|dish| self.contributions(dish, &resolution)),
+        )
+        .await?;
+        let mut totals = NutritionFacts::default();
+        let mut ingredients = Vec::new();
+        for (ingredient, contribution, grams) in contributions.into_iter().flatten() {
+            if !self.counts(ingredient, &options.ingredients) {
+                continue;
+            }
+            totals = totals + contribution.clone();
+            ingredients.push(IngredientStep {
+                product: ingredient.product.clone(),
+                amount: ingredient.amount,
+                unit: ingredient.unit.clone(),
+                to_taste: ingredient.to_taste,
+                grams,
+                facts: (grams > 0.0).then(|| contribution.clone() * (100.0 / grams)),
+                contribution,
+            });
+        }
+        let computed = self.compute_in(&resolution).await?;
+        Ok(Explanation {
+            ingredients,
+            totals,
+            computed: ComputedRecipe {
+                facts: computed.facts.rounded(options.rounding),
+                ..computed
+            },
+        })
+    }
+
+    async fn get_nutrition_facts_in(
+        &self,
+        resolution: &Resolution<'_>,
+    ) -> Result<NutritionFacts, RecipeError> {
+        Ok(self.compute_in(resolution).await?.facts)
+    }
+
+    #[async_recursion]
+    async fn compute_in(&self, resolution: &Resolution<'_>) -> Result<ComputedRecipe, RecipeError> {
+        let dishes = self.dishes();
+        let totals =
+            try_join_all(dishes.iter().map(|dish| self.aggregate(dish, resolution))).await?;
+        let mut accumulator = NutritionAccumulator::new();
+        let mut dish_weight = 0.0;
+        for (dish, (counted, total_ingredients_weight)) in dishes.into_iter().zip(totals) {
+            if resolution.options.strict_weights
+                && dish.weight.is_none()
+                && dish.cooked_weight.is_none()
+            {
+                return Err(RecipeError::MissingWeight);
+            }
+            if resolution.options.strict_weights {
+                if let Some(&(ingredient, grams, dish_weight)) = self.overweight_in(dish).first() {
+                    return Err(RecipeError::OverweightIngredient {
+                        ingredient: ingredient.into(),
+                        grams,
+                        dish_weight,
+                    });
+                }
+            }
+            let counted_weight = counted.raw_weight();
+            accumulator.add(counted.total_nutrition().clone(), counted_weight);
+            dish_weight += if counted_weight == total_ingredients_weight {
+                dish.final_weight(total_ingredients_weight)
+            } else {
+                dish.final_weight(total_ingredients_weight) * counted_weight
+                    / total_ingredients_weight
+            };
+        }
+
+        if dish_weight <= 0.0 {
+            return Err(RecipeError::ZeroWeight);
+        }
+
+        Ok(ComputedRecipe {
+            raw_weight: accumulator.raw_weight(),
+            facts: accumulator.finalize(dish_weight),
+            dish_weight,
+        })
+    }
+
+    /// Name of the product an ingredient refers to by `name`, either an alias
+    /// or the name itself, ignoring case of aliases like of product names.
+    fn product_name<'a>(&'a self, name: &'a str) -> &'a str {
+        let normalized = normalize_name(name);
+        self.aliases
+            .iter()
+            .find(|(alias, _)| normalize_name(alias) == normalized)
+            .map_or(name, |(_, product)| product.as_str())
+    }
+
+    /// Product of `ingredient`, `None` when it's missing and `options` allow
+    /// missing ingredients.
+    fn used_product(
+        &self,
+        ingredient: &Ingredient,
+        options: &ComputeOptions,
+    ) -> Result<Option<&Product>, RecipeError> {
+        match self.find_product(&ingredient.product) {
+            Ok(product) => Ok(Some(product)),
+            Err(RecipeError::IngredientNotFound { .. }) if options.allow_missing => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn find_product(&self, name: &str) -> Result<&Product, RecipeError> {
+        let product_name = self.product_name(name);
+        self.products
+            .iter()
+            .find(|p| p.is_named(product_name))
+            .ok_or_else(|| RecipeError::IngredientNotFound {
+                ingredient: name.into(),
+                available: self.products.iter().map(|p| p.name.clone()).collect(),
+                suggestion: self.closest_product(product_name).map(|p| p.name.clone()),
+            })
+    }
+
+    /// Product whose name is a few typos away from `name`, at most one edit
+    /// for every three characters.
+    fn closest_product(&self, name: &str) -> Option<&Product> {
+        let name = normalize_name(name);
+        let max_distance = (name.chars().count() / 3).max(1);
+        self.products
+            .iter()
+            .map(|p| (strsim::levenshtein(&normalize_name(&p.name), &name), p))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, p)| p)
+    }
+
+    /// Sums nutrients contributed by the ingredients of `dish` the options
+    /// of a top-level recipe count, returned together with the raw weight of
+    /// all of them.
+    async fn aggregate(
+        &self,
+        dish: &Dish,
+        resolution: &Resolution<'_>,
+    ) -> Result<(NutritionAccumulator, f64), RecipeError> {
+        let filter = match resolution.depth {
+            0 => &resolution.options.ingredients,
+            _ => &IngredientFilter::All,
+        };
+        let mut counted = NutritionAccumulator::new();
+        let mut total_ingredients_weight = 0.0;
+        for (ingredient, facts, grams) in self.contributions(dish, resolution).await? {
+            total_ingredients_weight += grams;
+            if self.counts(ingredient, filter) {
+                counted.add(facts, grams);
+            }
+        }
+        debug!(
+            "Totals for raw ingredients {:?} {}",
+            counted.total_nutrition(),
+            counted.raw_weight()
+        );
+
+        Ok((counted, total_ingredients_weight))
+    }
+
+    /// Whether `filter` counts `ingredient`, names matching its product like
+    /// at lookup, aliases included.
+    fn counts(&self, ingredient: &Ingredient, filter: &IngredientFilter) -> bool {
+        let product = normalize_name(self.product_name(&ingredient.product));
+        let named = |names: &[String]| {
+            names
+                .iter()
+                .any(|name| normalize_name(self.product_name(name)) == product)
+        };
+        match filter {
+            IngredientFilter::All => true,
+            IngredientFilter::Only(names) => named(names),
+            IngredientFilter::Exclude(names) => !named(names),
+        }
+    }
+
+    /// Facts per 100g of a blend of `components`, the average of theirs
+    /// weighted by percentage. `blends` holds the blends being averaged,
+    /// outermost first, to catch blends made of themselves.
+    #[async_recursion]
+    async fn blend_facts(
+        &self,
+        components: &[BlendComponent],
+        base: Option<&'async_recursion Path>,
+        blends: &mut Vec<String>,
+        resolution: &Resolution<'_>,
+    ) -> Result<NutritionFacts, RecipeError> {
+        let mut facts = NutritionFacts::default();
+        for component in components {
+            let product = self.find_product(&component.product)?;
+            let component_facts = self
+                .product_facts_in(product, base, blends, resolution)
+                .await?;
+            facts = facts + component_facts * (component.percent / 100.0);
+        }
+        Ok(facts)
+    }
+
+    /// Facts per 100g of `product`, whatever they come from. `blends` holds
+    /// the blends `product` is a component of, like for
+    /// [`Recipe::blend_facts`].
+    async fn product_facts_in(
+        &self,
+        product: &Product,
+        base: Option<&Path>,
+        blends: &mut Vec<String>,
+        resolution: &Resolution<'_>,
+    ) -> Result<NutritionFacts, RecipeError> {
+        let facts = match product.canonical_nutrition_data(base).await? {
+            NutritionData::Facts(facts) => resolution.options.product_facts(facts),
+            NutritionData::Range(range) => {
+                let options = resolution.options;
+                options.product_facts(range.at(options.bound))
+            }
+            NutritionData::Recipe(path, _) => resolution.resolve(path).await?.facts,
+            NutritionData::Blend(components) => {
+                enter_blend(blends, product)?;
+                let facts = self
+                    .blend_facts(&components, base, blends, resolution)
+                    .await?;
+                blends.pop();
+                facts
+            }
+        };
+        Ok(product.scaled(facts))
+    }
+
+    /// Nutrients every ingredient of `dish` contributes by its amount, in the
+    /// order of ingredients, with the grams the ingredient weighs.
+    async fn contributions<'a>(
+        &self,
+        dish: &'a Dish,
+        resolution: &Resolution<'_>,
+    ) -> Result<Vec<(&'a Ingredient, NutritionFacts, f64)>, RecipeError> {
+        let mut used = Vec::with_capacity(dish.ingredients.len());
+        for ingredient in &dish.ingredients {
+            match self.used_product(ingredient, resolution.options)? {
+                Some(product) => used.push((ingredient, product)),
+                None => warn!("Skipped missing ingredient {}", ingredient.product),
+            }
+        }
+
+        // Sub-recipes are resolved concurrently, each distinct file once, while
+        // the sums below always follow the order of ingredients.
+        let base = self.path.as_deref().and_then(Path::parent);
+        let data = try_join_all(
+            used.iter()
+                .map(|(_, product)| product.canonical_nutrition_data(base)),
+        )
+        .await?;
+        let mut paths: Vec<PathBuf> = data
+            .iter()
+            .filter_map(|data| match data {
+                NutritionData::Recipe(path, _) => Some(path.clone()),
+                NutritionData::Facts(_) | NutritionData::Range(_) | NutritionData::Blend(_) => None,
+            })
+            .collect();
+        paths.sort();
+        paths.dedup();
+        let computed =
+            try_join_all(paths.iter().map(|path| resolution.resolve(path.clone()))).await?;
+        let computed: HashMap<PathBuf, ComputedRecipe> = paths.into_iter().zip(computed).collect();
+
+        // Sub-recipes resolved on the way are timed as they're read, parsed
+        // and aggregated themselves, so the clock stops for them.
+        let stop_clock = |start| {
+            if let Some(timings) = &resolution.options.timings {
+                timings.add_aggregation(start);
+            }
+        };
+        let mut start = Instant::now();
+        let mut contributions = Vec::with_capacity(used.len());
+        for ((ingredient, product), data) in used.into_iter().zip(data) {
+            let mut grams = None;
+            let facts = match data {
+                NutritionData::Facts(facts) => resolution.options.product_facts(facts),
+                NutritionData::Range(range) => {
+                    let options = resolution.options;
+                    options.product_facts(range.at(options.bound))
+                }
+                NutritionData::Recipe(path, _) => {
+                    let sub_recipe = match computed.get(&path) {
+                        Some(computed) => computed.clone(),
+                        None => {
+                            stop_clock(start);
+                            let resolved = resolution.resolve(path).await?;
+                            start = Instant::now();
+                            resolved
+                        }
+                    };
+                    if ingredient.counts_dishes(product)? {
+                        grams = Some(ingredient.amount * sub_recipe.dish_weight);
+                    }
+                    sub_recipe.facts
+                }
+                NutritionData::Blend(components) => {
+                    let mut blends = vec![product.name.clone()];
+                    stop_clock(start);
+                    let facts = self
+                        .blend_facts(&components, base, &mut blends, resolution)
+                        .await?;
+                    start = Instant::now();
+                    facts
+                }
+            };
+            let grams = match grams {
+                Some(grams) => grams,
+                None => ingredient.grams(product, dish.weight)?,
+            };
+            let contribution = product.scaled(facts) * (grams / 100.0);
+            debug!("add {} {}g = {:?}", product.name, grams, contribution);
+            contributions.push((ingredient, contribution, grams));
+        }
+        stop_clock(start);
+        Ok(contributions)
+    }
+}
+
+/// Builds a recipe of a single dish in code, as an alternative to YAML.
+///
+/// ```
+/// use nutritions::{Ingredient, Nutrition, Product, RecipeBuilder};
+///
+/// let recipe = RecipeBuilder::new()
+///     .product(Product::new("Oil", [(Nutrition::Energy, 900.0)]))
+///     .ingredient(Ingredient::new("Oil", 50.0))
+///     .servings(2.0)
+///     .build()?;
+/// let facts = futures::executor::block_on(recipe.get_nutrition_facts_per_serving())?;
+///
+/// assert_eq!(facts.get(Nutrition::Energy), Some(225.0));
+/// # Ok::<(), nutritions::RecipeError>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct RecipeBuilder {
+    products: Vec<Product>,
+    dish: Dish,
+}
+
+impl RecipeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn product(mut self, product: Product) -> Self {
+        self.products.push(product);
+        self
+    }
+
+    pub fn ingredient(mut self, ingredient: Ingredient) -> Self {
+        self.dish.ingredients.push(ingredient);
+        self
+    }
+
+    pub fn weight(mut self, weight: f64) -> Self {
+        self.dish.weight = Some(weight);
+        self
+    }
+
+    pub fn cooked_weight(mut self, weight: f64) -> Self {
+        self.dish.cooked_weight = Some(weight);
+        self
+    }
+
+    pub fn servings(mut self, servings: f64) -> Self {
+        self.dish.servings = Some(servings);
+        self
+    }
+
+    /// Validates the recipe like one parsed from YAML. Relative sub-recipe
+    /// paths are resolved against the current directory.
+    pub fn build(self) -> Result<Recipe, RecipeError> {
+        let mut recipe = Recipe {
+            include: Vec::new(),
+            products: self.products,
+            aliases: HashMap::new(),
+            dishes: Dishes::Dish(self.dish),
+            metadata: Metadata::default(),
+            path: None,
+            libraries: Vec::new(),
+        };
+        recipe.validate()?;
+        recipe.normalize_bases()?;
+        Ok(recipe)
+    }
+}
+
+/// Whether the file at `path` is gzipped, as told by its `.gz` extension.
+fn is_gzipped(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gz"))
+}
+
+/// Contents of a recipe or library file, decompressed when it's gzipped.
+async fn read_text(path: &Path) -> Result<String, RecipeError> {
+    if !is_gzipped(path) {
+        return Ok(tokio::fs::read_to_string(path).await?);
+    }
+    let compressed = tokio::fs::read(path).await?;
+    let mut text = String::new();
+    flate2::read::GzDecoder::new(compressed.as_slice()).read_to_string(&mut text)?;
+    Ok(text)
+}
+
+impl PartialEq for Recipe {
+    fn eq(&self, other: &Recipe) -> bool {
+        self.include == other.include
+            && self.products == other.products
+            && self.aliases == other.aliases
+            && self.dishes == other.dishes
+            && self.metadata == other.metadata
+    }
+}
+
+impl Eq for Recipe {}
+
+impl Hash for Recipe {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.include.hash(state);
+        self.products.hash(state);
+        let aliases: BTreeMap<_, _> = self.aliases.iter().collect();
+        aliases.hash(state);
+        self.dishes.hash(state);
+        self.metadata.hash(state);
+    }
+}
+
+/// Bit pattern amounts are compared and hashed by, the same for every NaN
+/// and for negative zero and zero, so that `==` on them is reflexive.
+fn amount_key(amount: f64) -> u64 {
+    if amount.is_nan() {
+        f64::NAN.to_bits()
+    } else {
+        (amount + 0.0).to_bits()
+    }
+}
+
+fn optional_amount_key(amount: Option<f64>) -> Option<u64> {
+    amount.map(amount_key)
+}
+
+fn hash_amount<H: Hasher>(amount: f64, state: &mut H) {
+    amount_key(amount).hash(state);
+}
+
+fn hash_optional_amount<H: Hasher>(amount: Option<f64>, state: &mut H) {
+    optional_amount_key(amount).hash(state);
+}
+
+impl PartialEq for NutritionFacts {
+    fn eq(&self, other: &NutritionFacts) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(&other.0)
+                .all(|((a, x), (b, y))| a == b && amount_key(*x) == amount_key(*y))
+    }
+}
+
+impl PartialEq for Dish {
+    fn eq(&self, other: &Dish) -> bool {
+        self.ingredients == other.ingredients
+            && optional_amount_key(self.weight) == optional_amount_key(other.weight)
+            && optional_amount_key(self.cooked_weight) == optional_amount_key(other.cooked_weight)
+            && optional_amount_key(self.servings) == optional_amount_key(other.servings)
+    }
+}
+
+impl PartialEq for Ingredient {
+    fn eq(&self, other: &Ingredient) -> bool {
+        self.product == other.product
+            && amount_key(self.amount) == amount_key(other.amount)
+            && self.unit == other.unit
+            && self.to_taste == other.to_taste
+    }
+}
+
+impl PartialEq for Product {
+    fn eq(&self, other: &Product) -> bool {
+        let same =
+            |a: Option<f64>, b: Option<f64>| optional_amount_key(a) == optional_amount_key(b);
+        self.name == other.name
+            && same(self.density_g_per_ml, other.density_g_per_ml)
+            && same(self.grams_per_piece, other.grams_per_piece)
+            && same(self.serving_weight_g, other.serving_weight_g)
+            && self.per == other.per
+            && self.basis == other.basis
+            && same(self.scale, other.scale)
+            && same(self.price_per_100g, other.price_per_100g)
+            && self.allergens == other.allergens
+            && self.nutrition_data == other.nutrition_data
+    }
+}
+
+impl PartialEq for Basis {
+    fn eq(&self, other: &Basis) -> bool {
+        amount_key(self.amount) == amount_key(other.amount) && self.unit == other.unit
+    }
+}
+
+impl PartialEq for BlendComponent {
+    fn eq(&self, other: &BlendComponent) -> bool {
+        self.product == other.product && amount_key(self.percent) == amount_key(other.percent)
+    }
+}
+
+impl Eq for NutritionFacts {}
+impl Eq for Dish {}
+impl Eq for Ingredient {}
+impl Eq for Product {}
+impl Eq for Basis {}
+impl Eq for BlendComponent {}
+
+impl Hash for NutritionFacts {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.0.len());
+        for (nutrient, amount) in &self.0 {
+            nutrient.hash(state);
+            hash_amount(*amount, state);
+        }
+    }
+}
+
+impl Hash for Dish {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ingredients.hash(state);
+        hash_optional_amount(self.weight, state);
+        hash_optional_amount(self.cooked_weight, state);
+        hash_optional_amount(self.servings, state);
+    }
+}
+
+impl Hash for Ingredient {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.product.hash(state);
+        hash_amount(self.amount, state);
+        self.unit.hash(state);
+        self.to_taste.hash(state);
+    }
+}
+
+impl Hash for Product {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        hash_optional_amount(self.density_g_per_ml, state);
+        hash_optional_amount(self.grams_per_piece, state);
+        hash_optional_amount(self.serving_weight_g, state);
+        self.per.hash(state);
+        self.basis.hash(state);
+        hash_optional_amount(self.scale, state);
+        hash_optional_amount(self.price_per_100g, state);
+        self.allergens.hash(state);
+        self.nutrition_data.hash(state);
+    }
+}
+
+impl Hash for Basis {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_amount(self.amount, state);
+        self.unit.hash(state);
+    }
+}
+
+impl Hash for NutritionData {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            NutritionData::Facts(facts) => facts.hash(state),
+            NutritionData::Range(range) => range.hash(state),
+            NutritionData::Recipe(path, basis) => {
+                path.hash(state);
+                basis.hash(state);
+            }
+            NutritionData::Blend(components) => components.hash(state),
+        }
+    }
+}
+
+impl Hash for BlendComponent {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.product.hash(state);
+        hash_amount(self.percent, state);
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// How far percentages of a blend may add up from 100, to allow for
+/// rounding of fractions like thirds.
+const BLEND_TOLERANCE: f64 = 0.01;
+
+/// Records that the components of the blend `product` are being visited,
+/// failing with `BlendCycle` when they already are.
+fn enter_blend(blends: &mut Vec<String>, product: &Product) -> Result<(), RecipeError> {
+    if let Some(start) = blends.iter().position(|blend| product.is_named(blend)) {
+        let mut chain = blends[start..].to_vec();
+        chain.push(product.name.clone());
+        return Err(RecipeError::BlendCycle { chain });
+    }
+    blends.push(product.name.clone());
+    Ok(())
+}
+
+/// Fails with `InvalidValue` unless `value` is finite and satisfies `valid`.
+pub(crate) fn check(
+    field: impl FnOnce() -> String,
+    value: f64,
+    valid: impl FnOnce(f64) -> bool,
+) -> Result<(), RecipeError> {
+    if value.is_finite() && valid(value) {
+        Ok(())
+    } else {
+        Err(RecipeError::InvalidValue {
+            field: field(),
+            value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        ComputeOptions, Cost, Dishes, Ingredient, IngredientFilter, InputFormat, Metadata,
+        Nutrition, NutritionData, NutritionFacts, ParseOptions, Product, Recipe, RoundingPolicy,
+        SubtractPolicy,
+    };
+    use crate::{error::RecipeError, timings::Timings};
+    use futures::executor::block_on;
+    use proptest::{prop_assert, proptest, strategy::Strategy};
+    use std::{
+        collections::{HashMap, HashSet},
+        path::{Path, PathBuf},
+        sync::Arc,
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn calculate1() {
+        let oil = Product {
+            name: "Oil".into(),
+            density_g_per_ml: None,
+            grams_per_piece: None,
+            serving_weight_g: None,
+            per: None,
+            basis: None,
+            price_per_100g: None,
+            scale: None,
+            allergens: Vec::new(),
+            nutrition_data: NutritionData::Facts(NutritionFacts::from_iter([(
+                Nutrition::Energy,
+                1000.0,
+            )])),
+        };
+        let recipe = Recipe {
+            include: Vec::new(),
+            aliases: HashMap::new(),
+            dishes: Dishes::Dish(super::Dish {
+                ingredients: vec![Ingredient {
+                    product: "Oil".into(),
+                    amount: 10.0,
+                    unit: None,
+                    to_taste: false,
+                }],
+                weight: Some(20.0),
+                cooked_weight: None,
+                servings: None,
+            }),
+            products: vec![oil],
+            metadata: Default::default(),
+            path: None,
+            libraries: Vec::new(),
+        };
+
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+
+        assert_eq!(
+            facts,
+            NutritionFacts::from_iter([(Nutrition::Energy, 500.0)])
+        )
+    }
+
+    #[tokio::test]
+    async fn fail_not_found() {
+        let milk = Product {
+            name: "Milk".into(),
+            density_g_per_ml: None,
+            grams_per_piece: None,
+            serving_weight_g: None,
+            per: None,
+            basis: None,
+            price_per_100g: None,
+            scale: None,
+            allergens: Vec::new(),
+            nutrition_data: NutritionData::Facts(NutritionFacts::from_iter([(
+                Nutrition::Energy,
+                1000.0,
+            )])),
+        };
+        let oil = Product {
+            name: "Oil".into(),
+            density_g_per_ml: None,
+            grams_per_piece: None,
+            serving_weight_g: None,
+            per: None,
+            basis: None,
+            price_per_100g: None,
+            scale: None,
+            allergens: Vec::new(),
+            nutrition_data: NutritionData::Facts(NutritionFacts::from_iter([(
+                Nutrition::Energy,
+                1000.0,
+            )])),
+        };
+        let recipe = Recipe {
+            include: Vec::new(),
+            aliases: HashMap::new(),
+            dishes: Dishes::Dish(super::Dish {
+                ingredients: vec![Ingredient {
+                    product: "cabbage".into(),
+                    amount: 10.0,
+                    unit: None,
+                    to_taste: false,
+                }],
+                weight: Some(20.0),
+                cooked_weight: None,
+                servings: None,
+            }),
+            products: vec![oil, milk],
+            metadata: Default::default(),
+            path: None,
+            libraries: Vec::new(),
+        };
+
+        let err = recipe.get_nutrition_facts().await.unwrap_err();
+
+        assert!(matches!(err, RecipeError::IngredientNotFound { .. }));
+        assert_eq!(
+            err.to_string(),
+            "Cannot find ingredient in recipe: cabbage possible products: Oil, Milk"
+        )
+    }
+
+    #[tokio::test]
+    async fn allow_missing_ingredients() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Bread
+    facts:
+      Energy: 260
+    allergens: [Gluten]
+dish:
+  ingredients:
+    - product: Bread
+      amount: 100
+    - product: Butter
+      amount: 10
+"#,
+        )
+        .unwrap();
+        let lenient = ComputeOptions {
+            allow_missing: true,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            recipe.get_nutrition_facts().await,
+            Err(RecipeError::IngredientNotFound { .. })
+        ));
+        assert!(recipe.allergens().await.is_err());
+        assert_eq!(
+            recipe.get_nutrition_facts_with(&lenient).await.unwrap(),
+            NutritionFacts::from_iter([(Nutrition::Energy, 260.0)])
+        );
+        assert_eq!(recipe.allergens_with(&lenient).await.unwrap(), ["gluten"]);
+    }
+
+    #[tokio::test]
+    async fn fail_cycle() {
+        let recipe = Recipe::read_from_file(Path::new("tests/fixtures/cycle-a.yaml"))
+            .await
+            .unwrap();
+
+        let err = recipe.get_nutrition_facts().await.unwrap_err();
+
+        match err.root_cause() {
+            RecipeError::CycleDetected { path } => assert!(path.ends_with("cycle-a.yaml")),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn fail_in_sub_recipe() {
+        let recipe = Recipe::read_from_file(Path::new("tests/fixtures/breadcrumb/cake.yaml"))
+            .await
+            .unwrap();
+
+        let err = recipe.get_nutrition_facts().await.unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "in cake.yaml -> frosting.yaml: Cannot find ingredient in recipe: Sugar \
+             possible products: Butter"
+        );
+        match err {
+            RecipeError::InRecipe { chain, .. } => assert_eq!(chain.len(), 2),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn collect_timings() {
+        let timings = Arc::new(Timings::default());
+        let path = Path::new("tests/fixtures/nested-outer.yaml");
+        let recipe = Recipe::read_from_file_timed(path, InputFormat::Yaml, &timings)
+            .await
+            .unwrap();
+        let options = ComputeOptions {
+            timings: Some(timings.clone()),
+            ..Default::default()
+        };
+
+        recipe.get_nutrition_facts_with(&options).await.unwrap();
+
+        assert_eq!(timings.files(), 3);
+        assert!(timings.io() > Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn fail_max_depth() {
+        let recipe = Recipe::read_from_file(Path::new("tests/fixtures/nested-outer.yaml"))
+            .await
+            .unwrap();
+        let options = ComputeOptions {
+            max_depth: 1,
+            ..Default::default()
+        };
+
+        assert!(recipe.get_nutrition_facts().await.is_ok());
+        match recipe
+            .get_nutrition_facts_with(&options)
+            .await
+            .unwrap_err()
+            .root_cause()
+        {
+            RecipeError::MaxDepthExceeded { depth, path } => {
+                assert_eq!(*depth, 1);
+                assert!(path.ends_with("nested-inner.yaml"));
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn calculate_from_yaml_str() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Oil
+    facts:
+      Energy: 900
+      Fats: 100
+  - name: Flour
+    facts:
+      Energy: 350
+      Carbohydrates: 75
+dish:
+  weight: 250
+  ingredients:
+    - product: Oil
+      amount: 50
+    - product: Flour
+      amount: 200
+"#,
+        )
+        .unwrap();
+
+        let facts = futures::executor::block_on(recipe.get_nutrition_facts()).unwrap();
+
+        assert_eq!(
+            facts,
+            NutritionFacts::from_iter([
+                (Nutrition::Energy, 460.0),
+                (Nutrition::Fats, 20.0),
+                (Nutrition::Carbohydrates, 60.0)
+            ])
+        )
+    }
+
+    #[test]
+    fn calculate_cost() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Flour
+    price_per_100g: 0.2
+    facts:
+      Energy: 350
+  - name: Butter
+    price_per_100g: 1.5
+    facts:
+      Energy: 750
+  - name: Salt
+    facts:
+      Sodium: 38000
+dish:
+  servings: 4
+  ingredients:
+    - product: Flour
+      amount: 500
+    - product: Butter
+      amount: 200
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            recipe.total_cost().unwrap(),
+            Cost {
+                total: 4.0,
+                per_serving: Some(1.0)
+            }
+        );
+
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Salt
+    facts:
+      Sodium: 38000
+dish:
+  ingredients:
+    - product: Salt
+      amount: 5
+"#,
+        )
+        .unwrap();
+
+        match recipe.total_cost().unwrap_err() {
+            RecipeError::MissingPrice { product } => assert_eq!(product, "Salt"),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn calculate_cost_of_used_ingredients() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Flour
+    price_per_100g: 0.2
+    facts:
+      Energy: 350
+  - name: Salt
+    facts:
+      Sodium: 38000
+  - name: Pepper
+    facts:
+      Energy: 250
+dish:
+  ingredients:
+    - product: Flour
+      amount: 500
+    - product: Salt
+      to_taste: true
+    - product: Pepper
+      amount: 0
+    - product: Sugar
+      amount: 50
+"#,
+        )
+        .unwrap();
+        let allow_missing = ComputeOptions {
+            allow_missing: true,
+            ..ComputeOptions::default()
+        };
+
+        assert!(matches!(
+            recipe.total_cost(),
+            Err(RecipeError::IngredientNotFound { .. })
+        ));
+        assert_eq!(recipe.total_cost_with(&allow_missing).unwrap().total, 1.0);
+    }
+
+    #[tokio::test]
+    async fn calculate_cooked_weight() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Zucchini
+    facts:
+      Energy: 20
+  - name: Oil
+    facts:
+      Energy: 900
+dish:
+  cooked_weight: 150
+  ingredients:
+    - product: Zucchini
+      amount: 190
+    - product: Oil
+      amount: 10
+"#,
+        )
+        .unwrap();
+
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+
+        // 128 kcal of 200g raw ingredients end up in 150g.
+        assert_eq!(facts.to_string(), "Energy:  85.33 kcal\n");
+    }
+
+    #[tokio::test]
+    async fn override_weight() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Rice
+    facts:
+      Energy: 100
+  - name: Chicken
+    facts:
+      Energy: 300
+dishes:
+  - name: Garnish
+    cooked_weight: 300
+    ingredients:
+      - product: Rice
+        amount: 150
+  - name: Main
+    ingredients:
+      - product: Chicken
+        amount: 100
+"#,
+        )
+        .unwrap();
+
+        let weighed = recipe.with_weight(200.0).unwrap();
+        let facts = weighed.get_nutrition_facts().await.unwrap();
+        let (_, garnish) = &weighed.components()[0];
+
+        // 450 kcal in 200g instead of 400g, rice keeping 3/4 of the weight.
+        assert_eq!(
+            facts,
+            NutritionFacts::from_iter([(Nutrition::Energy, 225.0)])
+        );
+        assert_eq!(
+            garnish.get_nutrition_facts().await.unwrap(),
+            NutritionFacts::from_iter([(Nutrition::Energy, 100.0)])
+        );
+        assert!(matches!(
+            recipe.with_weight(0.0),
+            Err(RecipeError::InvalidValue { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn compute_weights() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Zucchini
+    facts:
+      Energy: 20
+dishes:
+  - name: Fried
+    cooked_weight: 150
+    ingredients:
+      - product: Zucchini
+        amount: 200
+  - name: Raw
+    ingredients:
+      - product: Zucchini
+        amount: 120
+"#,
+        )
+        .unwrap();
+
+        let computed = recipe.compute().await.unwrap();
+
+        assert_eq!(computed.raw_weight, 320.0);
+        assert_eq!(computed.dish_weight, 270.0);
+        assert_eq!(computed.facts, recipe.get_nutrition_facts().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn scale_proportions_to_weight() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Flour
+    facts:
+      Energy: 364
+      Carbohydrates: 76
+  - name: Water
+    facts: {}
+  - name: Salt
+    facts:
+      Sodium: 38758
+dish:
+  ingredients:
+    - product: Flour
+      amount: 3
+    - product: Water
+      amount: 2
+    - product: Salt
+      amount: 0.05
+"#,
+        )
+        .unwrap();
+
+        let dough = recipe.scaled_to(1000.0).unwrap();
+        let computed = dough.compute().await.unwrap();
+        let total = dough.get_total_nutrition().await.unwrap();
+
+        assert!((computed.raw_weight - 1000.0).abs() < 1e-9);
+        // 3 parts of 5.05 are flour.
+        let flour = 1000.0 * 3.0 / 5.05;
+        assert!((total.get(Nutrition::Energy).unwrap() - flour * 3.64).abs() < 1e-9);
+        assert_eq!(
+            dough.get_nutrition_facts().await.unwrap().to_string(),
+            recipe.get_nutrition_facts().await.unwrap().to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn find_aliased_products() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Olive Oil
+    facts:
+      Energy: 884
+aliases:
+  oo: Olive Oil
+  evoo: Olive Oli
+dish:
+  ingredients:
+    - product: OO
+      amount: 10
+"#,
+        )
+        .unwrap();
+
+        let facts = recipe.get_total_nutrition().await.unwrap();
+
+        assert_eq!(facts.get(Nutrition::Energy), Some(88.4));
+        assert!(recipe.unused_products().is_empty());
+        match recipe.find_product("evoo").err().unwrap() {
+            RecipeError::IngredientNotFound { suggestion, .. } => {
+                assert_eq!(suggestion.as_deref(), Some("Olive Oil"))
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn calculate_breakdown() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Rice
+    facts:
+      Energy: 100
+      Carbohydrates: 20
+  - name: Oil
+    density_g_per_ml: 0.9
+    facts:
+      Energy: 900
+dishes:
+  - name: Garnish
+    ingredients:
+      - product: Rice
+        amount: 150
+      - product: Oil
+        amount: 10
+        unit: ml
+  - name: Sauce
+    ingredients:
+      - product: Oil
+        amount: 20
+"#,
+        )
+        .unwrap();
+
+        let breakdown = recipe.breakdown().await.unwrap();
+
+        assert_eq!(
+            breakdown,
+            vec![
+                (
+                    "Rice".to_string(),
+                    NutritionFacts::from_iter([
+                        (Nutrition::Energy, 150.0),
+                        (Nutrition::Carbohydrates, 30.0)
+                    ])
+                ),
+                (
+                    "Oil".to_string(),
+                    NutritionFacts::from_iter([(Nutrition::Energy, 81.0)])
+                ),
+                (
+                    "Oil".to_string(),
+                    NutritionFacts::from_iter([(Nutrition::Energy, 180.0)])
+                ),
+            ]
+        );
+        let total = breakdown
+            .into_iter()
+            .fold(NutritionFacts::default(), |total, (_, facts)| total + facts);
+        assert_eq!(total, recipe.get_total_nutrition().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn calculate_components() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Rice
+    facts:
+      Energy: 100
+  - name: Chicken
+    facts:
+      Energy: 300
+dishes:
+  - name: Garnish
+    servings: 2
+    ingredients:
+      - product: Rice
+        amount: 150
+  - name: Main
+    servings: 1
+    ingredients:
+      - product: Chicken
+        amount: 50
+"#,
+        )
+        .unwrap();
+
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+        let per_serving = recipe.get_nutrition_facts_per_serving().await.unwrap();
+        let mut components = Vec::new();
+        for (name, component) in recipe.components() {
+            components.push((name, component.get_nutrition_facts().await.unwrap()));
+        }
+
+        assert_eq!(
+            facts,
+            NutritionFacts::from_iter([(Nutrition::Energy, 150.0)])
+        );
+        assert_eq!(
+            per_serving,
+            NutritionFacts::from_iter([(Nutrition::Energy, 225.0)])
+        );
+        assert_eq!(
+            components,
+            vec![
+                (
+                    "Garnish",
+                    NutritionFacts::from_iter([(Nutrition::Energy, 100.0)])
+                ),
+                (
+                    "Main",
+                    NutritionFacts::from_iter([(Nutrition::Energy, 300.0)])
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn calculate_estimated_energy() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Oil
+    facts:
+      Energy: 880
+      Fats: 100
+  - name: Chicken
+    facts:
+      Proteins: 20
+      Fats: 4
+dish:
+  ingredients:
+    - product: Oil
+      amount: 10
+    - product: Chicken
+      amount: 90
+"#,
+        )
+        .unwrap();
+        let options = ComputeOptions {
+            estimate_energy: true,
+            ..Default::default()
+        };
+
+        let facts = recipe.get_nutrition_facts_with(&options).await.unwrap();
+
+        assert_eq!(
+            facts.to_string(),
+            "Energy:  192.40 kcal\nProteins:  18.00 g\nFats:  13.60 g\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn calculate_all_nutrients() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Bread
+    facts:
+      Energy: 250
+      Proteins: 9
+      Fats: 3
+      Carbohydrates: 48
+      Fiber: 7
+      Sugars: 4
+      Sodium: 500
+dish:
+  ingredients:
+    - product: Bread
+      amount: 200
+"#,
+        )
+        .unwrap();
+
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+
+        assert_eq!(
+            facts.to_string(),
+            "Energy:  250.00 kcal\nProteins:  9.00 g\nFats:  3.00 g\nCarbohydrates:  48.00 g\n\
+             Fiber:  7.00 g\nSugars:  4.00 g\nSodium:  500.00 mg\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn calculate_custom_nutrients() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Orange
+    facts:
+      Energy: 47
+      vitamin_c: 53
+  - name: Salmon
+    facts:
+      Energy: 208
+      omega_3: 2.5
+dish:
+  ingredients:
+    - product: Orange
+      amount: 100
+    - product: Salmon
+      amount: 100
+"#,
+        )
+        .unwrap();
+
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+
+        assert_eq!(
+            facts.to_string(),
+            "Energy:  127.50 kcal\nomega_3:  1.25\nvitamin_c:  26.50\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn calculate_per_serving() {
+        let yaml = r#"
+products:
+  - name: Oil
+    facts:
+      Energy: 1000
+dish:
+  weight: 20
+  servings: 4
+  ingredients:
+    - product: Oil
+      amount: 10
+"#;
+        let recipe = Recipe::from_yaml_str(yaml).unwrap();
+
+        assert_eq!(
+            recipe.get_nutrition_facts_per_serving().await.unwrap(),
+            NutritionFacts::from_iter([(Nutrition::Energy, 25.0)])
+        );
+
+        let recipe = Recipe::from_yaml_str(&yaml.replace("servings: 4", "")).unwrap();
+
+        assert!(matches!(
+            recipe.get_nutrition_facts_per_serving().await,
+            Err(RecipeError::MissingServings)
+        ));
+    }
+
+    #[tokio::test]
+    async fn calculate_total() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Oil
+    facts:
+      Energy: 1000
+  - name: Flour
+    facts:
+      Energy: 300
+dish:
+  weight: 250
+  ingredients:
+    - product: Oil
+      amount: 10
+    - product: Flour
+      amount: 200
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            recipe.get_total_nutrition().await.unwrap(),
+            NutritionFacts::from_iter([(Nutrition::Energy, 700.0)])
+        );
+    }
+
+    #[tokio::test]
+    async fn calculate_ingredient_units() {
+        let yaml = r#"
+products:
+  - name: Flour
+    facts:
+      Energy: 300
+  - name: Butter
+    facts:
+      Energy: 700
+dish:
+  ingredients:
+    - product: Flour
+      amount: 0.5
+      unit: kg
+    - product: Butter
+      amount: 4
+      unit: oz
+"#;
+        let recipe = Recipe::from_yaml_str(yaml).unwrap();
+
+        let facts = recipe.get_total_nutrition().await.unwrap();
+
+        assert_eq!(facts.to_string(), "Energy:  2293.79 kcal\n");
+
+        let recipe = Recipe::from_yaml_str(&yaml.replace("unit: oz", "unit: stone")).unwrap();
+
+        match recipe.get_total_nutrition().await.unwrap_err() {
+            RecipeError::UnknownUnit { ingredient, unit } => {
+                assert_eq!(ingredient, "Butter");
+                assert_eq!(unit, "stone");
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn calculate_volume_units() {
+        let yaml = r#"
+products:
+  - name: Water
+    density_g_per_ml: 1.0
+    facts:
+      Energy: 0
+  - name: Oil
+    density_g_per_ml: 0.92
+    facts:
+      Energy: 900
+dish:
+  ingredients:
+    - product: Water
+      amount: 0.25
+      unit: l
+    - product: Oil
+      amount: 1
+      unit: tbsp
+"#;
+        let recipe = Recipe::from_yaml_str(yaml).unwrap();
+
+        assert_eq!(
+            recipe.get_total_nutrition().await.unwrap().to_string(),
+            "Energy:  124.20 kcal\n"
+        );
+        assert_eq!(
+            recipe.get_nutrition_facts().await.unwrap().to_string(),
+            "Energy:  47.08 kcal\n"
+        );
+
+        let recipe = Recipe::from_yaml_str(&yaml.replace("density_g_per_ml: 0.92", "")).unwrap();
+
+        match recipe.get_nutrition_facts().await.unwrap_err() {
+            RecipeError::MissingDensity { product } => assert_eq!(product, "Oil"),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn calculate_pieces() {
+        let yaml = r#"
+products:
+  - name: Egg
+    grams_per_piece: 50
+    facts:
+      Energy: 155
+      Proteins: 13
+dish:
+  ingredients:
+    - product: Egg
+      amount: 2
+      unit: pcs
+"#;
+        let recipe = Recipe::from_yaml_str(yaml).unwrap();
+
+        assert_eq!(
+            recipe.get_total_nutrition().await.unwrap().to_string(),
+            "Energy:  155.00 kcal\nProteins:  13.00 g\n"
+        );
+
+        let recipe = Recipe::from_yaml_str(&yaml.replace("grams_per_piece: 50", "")).unwrap();
+
+        match recipe.get_nutrition_facts().await.unwrap_err() {
+            RecipeError::MissingPieceWeight { product } => assert_eq!(product, "Egg"),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn calculate_facts_per_basis() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Crackers
+    per:
+      amount: 30
+    facts:
+      Energy: 120
+      Fats: 4.5
+  - name: Egg
+    grams_per_piece: 50
+    per:
+      amount: 1
+      unit: pcs
+    facts:
+      Energy: 78
+dish:
+  ingredients:
+    - product: Crackers
+      amount: 60
+    - product: Egg
+      amount: 100
+"#,
+        )
+        .unwrap();
+
+        let facts = recipe.get_total_nutrition().await.unwrap();
+
+        assert_eq!(facts.to_string(), "Energy:  396.00 kcal\nFats:  9.00 g\n");
+    }
+
+    #[tokio::test]
+    async fn calculate_facts_per_serving_weight() {
+        let yaml = r#"
+products:
+  - name: Cereal
+    serving_weight_g: 30
+    per:
+      amount: 1
+      unit: serving
+    facts:
+      Energy: 114
+      Sugars: 3.6
+dish:
+  ingredients:
+    - product: Cereal
+      amount: 100
+"#;
+        let recipe = Recipe::from_yaml_str(yaml).unwrap();
+
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+
+        assert_eq!(
+            facts.to_string(),
+            "Energy:  380.00 kcal\nSugars:  12.00 g\n"
+        );
+        match Recipe::from_yaml_str(&yaml.replace("serving_weight_g: 30", "")) {
+            Err(RecipeError::MissingServingWeight { product }) => assert_eq!(product, "Cereal"),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("facts per serving without its weight should be rejected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn merge_recipes() {
+        let base = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Oats
+    facts:
+      Energy: 370
+  - name: Milk
+    facts:
+      Energy: 60
+dish:
+  weight: 250
+  ingredients:
+    - product: Oats
+      amount: 50
+    - product: Milk
+      amount: 200
+"#,
+        )
+        .unwrap();
+        let topping = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: milk
+    facts:
+      Energy: 60
+  - name: Honey
+    facts:
+      Energy: 300
+dish:
+  weight: 50
+  ingredients:
+    - product: Honey
+      amount: 20
+    - product: milk
+      amount: 30
+"#,
+        )
+        .unwrap();
+
+        let merged = base.merged(&topping).unwrap();
+
+        assert_eq!(merged.unused_products(), Vec::<&str>::new());
+        assert_eq!(
+            merged.get_nutrition_facts().await.unwrap().to_string(),
+            "Energy:  127.67 kcal\n"
+        );
+    }
+
+    #[test]
+    fn fail_merge_conflicting_products() {
+        let yaml = r#"
+products:
+  - name: Milk
+    facts:
+      Energy: 60
+dish:
+  ingredients:
+    - product: Milk
+      amount: 200
+"#;
+        let whole = Recipe::from_yaml_str(yaml).unwrap();
+        let skimmed = Recipe::from_yaml_str(&yaml.replace("60", "35")).unwrap();
+
+        match whole.merged(&skimmed).err().unwrap() {
+            RecipeError::DuplicateProduct { name } => assert_eq!(name, "Milk"),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn calculate_scaled() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Oil
+    facts:
+      Energy: 1000
+  - name: Flour
+    facts:
+      Energy: 300
+dish:
+  weight: 250
+  ingredients:
+    - product: Oil
+      amount: 10
+    - product: Flour
+      amount: 200
+"#,
+        )
+        .unwrap();
+        let doubled = recipe.scaled(2.0);
+
+        assert_eq!(
+            doubled.get_nutrition_facts().await.unwrap(),
+            recipe.get_nutrition_facts().await.unwrap()
+        );
+        assert_eq!(
+            doubled.get_total_nutrition().await.unwrap(),
+            NutritionFacts::from_iter([(Nutrition::Energy, 1400.0)])
+        );
+    }
+
+    #[test]
+    fn find_overweight_products() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Cheese
+    facts:
+      Proteins: 25
+      Fats: 33
+      Carbohydrates: 1
+      Water: 37
+  - name: Typo
+    facts:
+      Proteins: 25
+      Fats: 33
+      Carbohydrates: 10
+      Water: 37
+  - name: Slice
+    per:
+      amount: 50
+    facts:
+      Proteins: 12
+      Fats: 17
+      Water: 20
+dish:
+  ingredients:
+    - product: Cheese
+      amount: 100
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(recipe.overweight_products(), [("Typo", 105.0)]);
+    }
+
+    #[test]
+    fn subtract_facts() {
+        let salad =
+            NutritionFacts::from_iter([(Nutrition::Energy, 250.0), (Nutrition::Fats, 20.0)]);
+        let dressing =
+            NutritionFacts::from_iter([(Nutrition::Fats, 25.0), (Nutrition::Sodium, 300.0)]);
+
+        assert_eq!(
+            salad.clone() - dressing.clone(),
+            NutritionFacts::from_iter([
+                (Nutrition::Energy, 250.0),
+                (Nutrition::Fats, 0.0),
+                (Nutrition::Sodium, 0.0)
+            ])
+        );
+        assert_eq!(
+            salad.subtract(&dressing, SubtractPolicy::Negative),
+            NutritionFacts::from_iter([
+                (Nutrition::Energy, 250.0),
+                (Nutrition::Fats, -5.0),
+                (Nutrition::Sodium, -300.0)
+            ])
+        );
+    }
+
+    #[test]
+    fn known_nutrients_sorted() {
+        assert!(Nutrition::KNOWN.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(Nutrition::Sodium < Nutrition::from_name("Calcium"));
+    }
+
+    #[test]
+    fn custom_nutrients_order() {
+        let facts = NutritionFacts::from_iter([
+            (Nutrition::from_name("zinc"), 1.0),
+            (Nutrition::Sodium, 2.0),
+            (Nutrition::from_name("iron"), 3.0),
+            (Nutrition::from_name("Calcium"), 4.0),
+            (Nutrition::Energy, 5.0),
+            (Nutrition::from_name("beta-carotene"), 6.0),
+        ]);
+
+        assert_eq!(
+            facts.to_string(),
+            "Energy:  5.00 kcal\n\
+             Sodium:  2.00 mg\n\
+             Calcium:  4.00\n\
+             beta-carotene:  6.00\n\
+             iron:  3.00\n\
+             zinc:  1.00\n"
+        );
+        assert_eq!(
+            serde_json::to_string(&facts).unwrap(),
+            r#"{"Energy":5.0,"Sodium":2.0,"Calcium":4.0,"beta-carotene":6.0,"iron":3.0,"zinc":1.0}"#
+        );
+    }
+
+    #[test]
+    fn display_precision() {
+        let facts =
+            NutritionFacts::from_iter([(Nutrition::Energy, 120.456), (Nutrition::Fats, 0.5)]);
+
+        assert_eq!(facts.to_string(), "Energy:  120.46 kcal\nFats:  0.50 g\n");
+        assert_eq!(format!("{:.0}", facts), "Energy:  120 kcal\nFats:  0 g\n");
+        assert_eq!(
+            format!("{:.3}", facts),
+            "Energy:  120.456 kcal\nFats:  0.500 g\n"
+        );
+    }
+
+    #[test]
+    fn add_facts() {
+        let breakfast =
+            NutritionFacts::from_iter([(Nutrition::Energy, 400.0), (Nutrition::Proteins, 20.0)]);
+        let lunch =
+            NutritionFacts::from_iter([(Nutrition::Energy, 600.0), (Nutrition::Fats, 30.0)]);
+
+        assert_eq!(
+            breakfast + lunch,
+            NutritionFacts::from_iter([
+                (Nutrition::Energy, 1000.0),
+                (Nutrition::Proteins, 20.0),
+                (Nutrition::Fats, 30.0)
+            ])
+        );
+    }
+
+    #[test]
+    fn mul_facts() {
+        let facts =
+            NutritionFacts::from_iter([(Nutrition::Energy, 400.0), (Nutrition::Proteins, 20.0)]);
+
+        assert_eq!(
+            facts * 1.5,
+            NutritionFacts::from_iter([(Nutrition::Energy, 600.0), (Nutrition::Proteins, 30.0)])
+        );
+    }
+
+    #[tokio::test]
+    async fn explain_reading_sub_recipes_once() {
+        let recipe = Recipe::read_from_file(Path::new("tests/fixtures/shared-outer.yaml"))
+            .await
+            .unwrap();
+        let timings = Arc::new(Timings::default());
+        let options = ComputeOptions {
+            timings: Some(timings.clone()),
+            ..ComputeOptions::default()
+        };
+
+        let explanation = recipe.explain_with(&options).await.unwrap();
+
+        assert_eq!(timings.files(), 1);
+        assert_eq!(
+            explanation.computed.facts,
+            recipe.get_nutrition_facts().await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn shared_sub_recipe_read_once() {
+        let recipe = Recipe::read_from_file(Path::new("tests/fixtures/shared-outer.yaml"))
+            .await
+            .unwrap();
+        let timings = Arc::new(Timings::default());
+        let options = ComputeOptions {
+            timings: Some(timings.clone()),
+            ..ComputeOptions::default()
+        };
+
+        let facts = recipe.get_nutrition_facts_with(&options).await.unwrap();
+
+        assert_eq!(timings.files(), 1);
+        assert_eq!(
+            facts.to_string(),
+            "Energy:  18.00 kcal\nProteins:  0.90 g\nFats:  0.20 g\nCarbohydrates:  3.90 g\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn calculate_sub_recipe_relative_to_file() {
+        let recipe = Recipe::read_from_file(Path::new("tests/fixtures/relative/cake.yaml"))
+            .await
+            .unwrap();
+
+        let facts = recipe.get_total_nutrition().await.unwrap();
+
+        assert_eq!(facts.to_string(), "Energy:  1100.00 kcal\n");
+    }
+
+    #[tokio::test]
+    async fn facts_yaml_round_trip() {
+        let recipe = Recipe::read_from_file(Path::new("tests/fixtures/nested-inner.yaml"))
+            .await
+            .unwrap();
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+        let yaml = serde_yaml::to_string(&facts).unwrap();
+
+        let product_facts = yaml
+            .lines()
+            .map(|line| format!("      {}\n", line))
+            .collect::<String>();
+        let recipe = Recipe::from_yaml_str(&format!(
+            "products:\n  - name: Cooked\n    facts:\n{}dish:\n  ingredients:\n    - product: Cooked\n      amount: 100\n",
+            product_facts
+        ))
+        .unwrap();
+
+        assert_eq!(recipe.get_nutrition_facts().await.unwrap(), facts);
+    }
+
+    #[tokio::test]
+    async fn include_product_library() {
+        let recipe = Recipe::read_from_file(Path::new("tests/fixtures/library/shortbread.yaml"))
+            .await
+            .unwrap();
+
+        let facts = recipe.get_total_nutrition().await.unwrap();
+
+        assert_eq!(
+            facts.to_string(),
+            "Energy:  2950.00 kcal\nFats:  164.00 g\nCarbohydrates:  325.00 g\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn read_gzipped_files() {
+        let plain = Recipe::read_from_file(Path::new("tests/fixtures/gzip/shortbread.yaml"))
+            .await
+            .unwrap();
+        let gzipped = Recipe::read_from_file(Path::new("tests/fixtures/gzip/shortbread.yaml.gz"))
+            .await
+            .unwrap();
+        let uncompressed =
+            Recipe::read_from_file(Path::new("tests/fixtures/library/shortbread.yaml"))
+                .await
+                .unwrap();
+
+        let facts = uncompressed.get_total_nutrition().await.unwrap();
+        assert_eq!(plain.get_total_nutrition().await.unwrap(), facts);
+        assert_eq!(gzipped.get_total_nutrition().await.unwrap(), facts);
+        assert_eq!(
+            InputFormat::from_path(Path::new("salad.JSON.gz")),
+            InputFormat::Json
+        );
+    }
+
+    #[tokio::test]
+    async fn read_json_and_yaml() {
+        let yaml = Recipe::read_from_file(Path::new("tests/fixtures/formats/salad.yaml"))
+            .await
+            .unwrap();
+        let json = Recipe::read_from_file(Path::new("tests/fixtures/formats/salad.json"))
+            .await
+            .unwrap();
+
+        let facts = yaml.get_nutrition_facts().await.unwrap();
+        assert_eq!(json.get_nutrition_facts().await.unwrap(), facts);
+        assert!(facts.get(Nutrition::Fats).is_some());
+
+        let forced = Recipe::read_from_file_as(
+            Path::new("tests/fixtures/formats/salad.yaml"),
+            InputFormat::Json,
+        )
+        .await;
+        assert!(matches!(forced, Err(RecipeError::ParseJson(_))));
+    }
+
+    #[test]
+    fn locate_default_database() {
+        let path = |config_dir: Option<&str>, home: Option<&str>| {
+            super::database_path(config_dir.map(Into::into), home.map(Into::into))
+        };
+
+        assert_eq!(
+            path(Some("/etc/nutritions"), Some("/home/cook")),
+            Some(PathBuf::from("/etc/nutritions/products.yaml"))
+        );
+        assert_eq!(
+            path(None, Some("/home/cook")),
+            Some(PathBuf::from("/home/cook/.config/nutritions/products.yaml"))
+        );
+        assert_eq!(path(None, None), None);
+    }
+
+    #[tokio::test]
+    async fn include_default_database() {
+        let yaml = r#"
+products:
+  - name: oil
+    facts:
+      Energy: 800
+  - name: Dressing
+    blend:
+      - product: Oil
+        percent: 50
+      - product: Vinegar
+        percent: 50
+dish:
+  ingredients:
+    - product: Dressing
+      amount: 20
+    - product: Water
+      amount: 80
+"#;
+        let with_database = |path: &'static str| async move {
+            let mut recipe = Recipe::from_yaml_str(yaml).unwrap();
+            recipe
+                .include_database(PathBuf::from(path), ParseOptions::default())
+                .await
+                .unwrap();
+            recipe.validate().unwrap();
+            recipe
+        };
+
+        let recipe = with_database("tests/fixtures/default-db/config/products.yaml").await;
+        let missing = with_database("tests/fixtures/default-db/products.yaml").await;
+
+        let names: Vec<&str> = recipe.products.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["oil", "Dressing", "Water", "Vinegar"]);
+        assert!(recipe.unused_products().is_empty());
+        assert!(recipe.energy_discrepancies().is_empty());
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+        assert_eq!(facts.get(Nutrition::Energy), Some(82.0));
+        let missing = missing.get_nutrition_facts().await;
+        assert!(matches!(
+            missing,
+            Err(RecipeError::IngredientNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn locate_in_search_path() {
+        let dir = Path::new("tests/fixtures/search");
+        let search_path = std::env::join_paths(["", "tests/fixtures/search"]).unwrap();
+
+        let found = super::locate_in(Path::new("search-sauce.yaml"), None, Some(&search_path))
+            .await
+            .unwrap();
+        let missing =
+            super::locate_in(Path::new("search-soup.yaml"), None, Some(&search_path)).await;
+
+        assert_eq!(found, dir.join("search-sauce.yaml").canonicalize().unwrap());
+        match missing.unwrap_err() {
+            RecipeError::FileNotFound { path, searched } => {
+                assert_eq!(path, Path::new("search-soup.yaml"));
+                assert_eq!(
+                    searched,
+                    [
+                        Path::new("search-soup.yaml").to_path_buf(),
+                        dir.join("search-soup.yaml")
+                    ]
+                );
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn fail_duplicate_included_product() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+include:
+  - tests/fixtures/library/products.yaml
+products:
+  - name: butter
+    facts:
+      Energy: 720
+dish:
+  ingredients:
+    - product: Butter
+      amount: 100
+"#,
+        )
+        .unwrap();
+
+        match recipe.resolve_includes().await.err().unwrap() {
+            RecipeError::DuplicateProduct { name } => assert_eq!(name, "Butter"),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn collect_allergens() {
+        let recipe = Recipe::read_from_file(Path::new("tests/fixtures/allergens/pie.yaml"))
+            .await
+            .unwrap();
+
+        let allergens = recipe.allergens().await.unwrap();
+
+        assert_eq!(allergens, ["dairy", "gluten", "nuts"]);
+    }
+
+    #[tokio::test]
+    async fn fail_malformed_file_at_location() {
+        let path = Path::new("tests/fixtures/malformed.yaml");
+
+        let err = Recipe::read_from_file(path).await.err().unwrap();
+
+        match &err {
+            RecipeError::Parse {
+                path: Some(failed),
+                source,
+            } => {
+                assert_eq!(failed, path);
+                assert_eq!(source.location().map(|at| at.line()), Some(9));
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+        assert_eq!(
+            err.to_string(),
+            "tests/fixtures/malformed.yaml:9:6: did not find expected '-' indicator, \
+             while parsing a block collection at line 7 column 5"
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_malformed_yaml() {
+        let malformed = [
+            "",
+            "[]",
+            "{{{{",
+            "products: 5",
+            "products: []\ndish: {}",
+            "products: []\ndish:\n  ingredients:\n    - product: Oil\n",
+            "products:\n  - name: Oil\n    facts: {Energy: .nan}\ndish:\n  ingredients: []",
+            "products:\n  - name: Oil\n    facts: [1, 2]\ndish:\n  ingredients: []",
+            "products:\n  - name: Oil\n    density_g_per_ml: 0\n    facts: {}\ndish:\n  ingredients: []",
+            "products: []\ndish:\n  ingredients:\n    - product: Oil\n      amount: 1e400",
+            &format!("{}{}", "[".repeat(10_000), "]".repeat(10_000)),
+        ];
+        for yaml in malformed {
+            assert!(Recipe::from_yaml_str(yaml).is_err(), "parsed {:?}", yaml);
+        }
+
+        let uncomputable = [
+            "products: []\ndish:\n  ingredients: []",
+            "products: []\ndishes: []",
+            "products:\n  - name: Oil\n    facts: {}\ndish:\n  ingredients:\n    - product: Oil\n      amount: 0",
+            "products:\n  - name: Oil\n    facts: {}\ndish:\n  ingredients:\n    - product: oli\n      amount: 1",
+            "products:\n  - name: Oil\n    facts: {}\ndish:\n  ingredients:\n    - product: Oil\n      amount: 1\n      unit: bucket",
+            "products:\n  - name: Oil\n    recipe: tests/fixtures/missing.yaml\ndish:\n  ingredients:\n    - product: Oil\n      amount: 1",
+        ];
+        for yaml in uncomputable {
+            let recipe = Recipe::from_yaml_str(yaml).unwrap();
+            assert!(
+                recipe.get_nutrition_facts().await.is_err(),
+                "computed {:?}",
+                yaml
+            );
+        }
+    }
+
+    #[test]
+    fn fail_invalid_values() {
+        let yaml = r#"
+products:
+  - name: Oil
+    facts:
+      Energy: 900
+      Fats: 100
+dish:
+  weight: 100
+  ingredients:
+    - product: Oil
+      amount: 10
+"#;
+        assert!(Recipe::from_yaml_str(yaml).is_ok());
+
+        for (from, to, expected) in [
+            ("Fats: 100", "Fats: -5", "products[Oil].Fats"),
+            ("Energy: 900", "Energy: .nan", "products[Oil].Energy"),
+            ("Energy: 900", "Energy: .inf", "products[Oil].Energy"),
+            ("amount: 10", "amount: -10", "dish.ingredients[Oil].amount"),
+            ("weight: 100", "weight: 0", "dish.weight"),
+        ] {
+            match Recipe::from_yaml_str(&yaml.replace(from, to)) {
+                Err(RecipeError::InvalidValue { field, .. }) => assert_eq!(field, expected),
+                Err(err) => panic!("unexpected error: {}", err),
+                Ok(_) => panic!("{} should be rejected", to),
+            }
+        }
+    }
+
+    #[test]
+    fn find_unused_products() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Oil
+    facts:
+      Energy: 900
+  - name: Milk
+    facts:
+      Energy: 60
+  - name: Flour
+    facts:
+      Energy: 350
+dish:
+  ingredients:
+    - product: Flour
+      amount: 100
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(recipe.unused_products(), vec!["Oil", "Milk"]);
+    }
+
+    #[tokio::test]
+    async fn match_products_ignoring_case_and_whitespace() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Olive Oil
+    facts:
+      Energy: 900
+  - name: flour
+    facts:
+      Energy: 350
+dish:
+  ingredients:
+    - product: "Olive Oil "
+      amount: 10
+    - product: FLOUR
+      amount: 100
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            recipe.get_total_nutrition().await.unwrap(),
+            NutritionFacts::from_iter([(Nutrition::Energy, 440.0)])
+        );
+        assert!(recipe.unused_products().is_empty());
+    }
+
+    #[test]
+    fn fail_not_found_with_suggestion() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Olive Oil
+    facts:
+      Energy: 900
+  - name: Milk
+    facts:
+      Energy: 60
+dish:
+  ingredients:
+    - product: Olive Oli
+      amount: 10
+"#,
+        )
+        .unwrap();
+
+        let err = recipe.find_product("Olive Oli").err().unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "Cannot find ingredient in recipe: Olive Oli possible products: Olive Oil, Milk, \
+             did you mean 'Olive Oil'?"
+        );
+        assert!(recipe.closest_product("Butter").is_none());
+    }
+
+    #[test]
+    fn fail_duplicate_product() {
+        let result = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Milk
+    facts:
+      Energy: 60
+  - name: Oil
+    facts:
+      Energy: 900
+  - name: milk
+    facts:
+      Energy: 42
+dish:
+  ingredients:
+    - product: Milk
+      amount: 100
+"#,
+        );
+
+        match result {
+            Err(RecipeError::DuplicateProduct { name }) => assert_eq!(name, "milk"),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("duplicate product should be rejected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn parse_metadata() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+metadata:
+  source: Grandma's notebook
+  author: Ann
+  tags: [breakfast, quick]
+  notes: Best with fresh butter.
+products:
+  - name: Oats
+    facts:
+      Energy: 370
+dish:
+  ingredients:
+    - product: Oats
+      amount: 100
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            recipe.metadata(),
+            &Metadata {
+                source: Some("Grandma's notebook".into()),
+                author: Some("Ann".into()),
+                tags: vec!["breakfast".into(), "quick".into()],
+                notes: Some("Best with fresh butter.".into()),
+            }
+        );
+        assert!(recipe.metadata().has_tag("quick"));
+        assert!(!recipe.metadata().has_tag("dinner"));
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+        assert_eq!(facts.get(Nutrition::Energy), Some(370.0));
+    }
+
+    const BREAD_AND_CHEESE: &str = r#"
+products:
+  - name: Bread
+    facts:
+      Energy: 250
+  - name: Cheese
+    facts:
+      Energy: 350
+aliases:
+  loaf: Bread
+dish:
+  weight: 240
+  ingredients:
+    - product: Bread
+      amount: 200
+    - product: Cheese
+      amount: 100
+"#;
+
+    #[tokio::test]
+    async fn calculate_only_ingredients() {
+        let recipe = Recipe::from_yaml_str(BREAD_AND_CHEESE).unwrap();
+        let options = ComputeOptions {
+            ingredients: IngredientFilter::Only(vec!["Loaf".into()]),
+            ..ComputeOptions::default()
+        };
+
+        let computed = recipe.compute_with(&options).await.unwrap();
+
+        // 500 kcal of bread in its 160g share of the dish.
+        assert_eq!(computed.raw_weight, 200.0);
+        assert_eq!(computed.dish_weight, 160.0);
+        assert_eq!(computed.facts.get(Nutrition::Energy), Some(312.5));
+    }
+
+    #[tokio::test]
+    async fn calculate_excluding_ingredients() {
+        let recipe = Recipe::from_yaml_str(BREAD_AND_CHEESE).unwrap();
+        let options = ComputeOptions {
+            ingredients: IngredientFilter::Exclude(vec![" bread ".into()]),
+            ..ComputeOptions::default()
+        };
+
+        let facts = recipe.get_nutrition_facts_with(&options).await.unwrap();
+        let all = recipe.get_nutrition_facts().await.unwrap();
+
+        // 350 kcal of cheese in its 80g share of the dish.
+        assert_eq!(facts.get(Nutrition::Energy), Some(437.5));
+        assert_eq!(all.get(Nutrition::Energy), Some(850.0 / 2.4));
+    }
+
+    #[tokio::test]
+    async fn write_canonical_string() {
+        let yaml = r#"
+products:
+  - name: Cheese
+    facts:
+      Zinc: 4
+      Fats: 30
+      Energy: 350
+  - name: Bread
+    facts:
+      Energy: 250
+      Carbohydrates: 50
+      Calcium: 0.1
+dish:
+  ingredients:
+    - product: Bread
+      amount: 200
+    - product: Cheese
+      amount: 100
+"#;
+        let compute = || async {
+            let recipe = Recipe::from_yaml_str(yaml).unwrap();
+            let facts = recipe.get_nutrition_facts().await.unwrap();
+            facts.to_canonical_string()
+        };
+
+        let first = compute().await;
+        let second = compute().await;
+
+        assert_eq!(first.as_bytes(), second.as_bytes());
+        assert_eq!(
+            first,
+            "Energy: 283.333 kcal\nFats: 10.000 g\nCarbohydrates: 33.333 g\nCalcium: 0.067\nZinc: 1.333\n"
+        );
+        assert_eq!(NutritionFacts::new().to_canonical_string(), "");
+    }
+
+    #[tokio::test]
+    async fn parse_decimal_comma() {
+        let yaml = r#"
+products:
+  - name: Cheese
+    facts:
+      Energy: 350
+      Fats: 10,5
+      Sodium:
+        min: 600,5
+        max: 700
+dish:
+  ingredients:
+    - product: Cheese
+      amount: 100
+"#;
+        let options = ParseOptions {
+            decimal_comma: true,
+            ..ParseOptions::default()
+        };
+
+        let recipe = Recipe::from_str_with(yaml, InputFormat::Yaml, options).unwrap();
+        let range = recipe.get_nutrition_range().await.unwrap();
+
+        assert_eq!(range.min.get(Nutrition::Fats), Some(10.5));
+        assert_eq!(range.min.get(Nutrition::Sodium), Some(600.5));
+        assert_eq!(range.max.get(Nutrition::Energy), Some(350.0));
+        assert!(Recipe::from_str_as(yaml, InputFormat::Yaml).is_err());
+        match Recipe::from_str_with(&yaml.replace("350", "lots"), InputFormat::Yaml, options) {
+            Err(RecipeError::Parse { source, .. }) => {
+                assert_eq!(source.location().map(|at| at.line()), Some(3))
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("a textual amount should be rejected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn list_ingredients_to_taste() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Potatoes
+    facts:
+      Energy: 80
+  - name: Mustard
+    allergens: [mustard]
+    facts:
+      Energy: 400
+  - name: Celery salt
+    allergens: [celery]
+    facts:
+      Sodium: 30000
+dish:
+  ingredients:
+    - product: Potatoes
+      amount: 500
+    - product: Mustard
+      to_taste: true
+    - product: Celery salt
+      amount: 0
+"#,
+        )
+        .unwrap();
+
+        let computed = recipe.compute().await.unwrap();
+        let breakdown = recipe.breakdown().await.unwrap();
+
+        assert_eq!(recipe.allergens().await.unwrap(), ["celery", "mustard"]);
+        assert_eq!(computed.raw_weight, 500.0);
+        assert_eq!(computed.facts.get(Nutrition::Energy), Some(80.0));
+        assert_eq!(computed.facts.get(Nutrition::Sodium), Some(0.0));
+        let names: Vec<&str> = breakdown.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["Potatoes", "Mustard", "Celery salt"]);
+        assert_eq!(breakdown[1].1.get(Nutrition::Energy), Some(0.0));
+        assert!(matches!(
+            Recipe::from_yaml_str(
+                "products:\n  - name: Salt\n    facts:\n      Sodium: 39000\ndish:\n  ingredients:\n    - product: Salt\n      amount: 5\n      to_taste: true\n"
+            ),
+            Err(RecipeError::InvalidValue { field, .. }) if field == "dish.ingredients[Salt].amount"
+        ));
+    }
+
+    #[tokio::test]
+    async fn resolve_products() {
+        let recipe = Recipe::read_from_file(Path::new("tests/fixtures/relative/cake.yaml"))
+            .await
+            .unwrap();
+
+        let products = recipe.resolved_products().await.unwrap();
+
+        let energies: Vec<(&str, Option<f64>)> = products
+            .iter()
+            .map(|(name, facts)| (name.as_str(), facts.get(Nutrition::Energy)))
+            .collect();
+        assert_eq!(
+            energies,
+            [("Sponge", Some(300.0)), ("Frosting", Some(400.0))]
+        );
+
+        let broken = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Jam
+    recipe: tests/fixtures/missing.yaml
+dish:
+  ingredients: []
+"#,
+        )
+        .unwrap();
+        match broken.resolved_products().await {
+            Err(RecipeError::InProduct { product, source }) => {
+                assert_eq!(product, "Jam");
+                assert!(matches!(*source, RecipeError::FileNotFound { .. }));
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("missing sub-recipe should fail"),
+        }
+    }
+
+    #[tokio::test]
+    async fn list_files() {
+        let recipe = Recipe::read_from_file(Path::new("tests/fixtures/cycle-a.yaml"))
+            .await
+            .unwrap();
+        let names = |files: Vec<std::path::PathBuf>| -> Vec<String> {
+            files
+                .iter()
+                .map(|file| file.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        assert_eq!(
+            names(recipe.files().await.unwrap()),
+            ["cycle-a.yaml", "cycle-b.yaml"]
+        );
+        let recipe = Recipe::read_from_file(Path::new("tests/fixtures/library/shortbread.yaml"))
+            .await
+            .unwrap();
+        assert_eq!(
+            names(recipe.files().await.unwrap()),
+            ["shortbread.yaml", "products.yaml"]
+        );
+    }
+
+    #[tokio::test]
+    async fn list_files_of_broken_sub_recipes() {
+        let dir = std::env::temp_dir().join(format!("nutritions-files-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("soup.yaml"),
+            "products:\n  - name: Stock\n    recipe: stock.yaml\n\
+             dish:\n  ingredients:\n    - product: Stock\n      amount: 100\n",
+        )
+        .unwrap();
+        let recipe = Recipe::read_from_file(&dir.join("soup.yaml"))
+            .await
+            .unwrap();
+        let names = |files: Vec<PathBuf>| -> Vec<String> {
+            files
+                .iter()
+                .map(|file| file.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        let missing = names(recipe.files().await.unwrap());
+        std::fs::write(dir.join("stock.yaml"), "products: [\n").unwrap();
+        let broken = names(recipe.files().await.unwrap());
+        std::fs::write(
+            dir.join("stock.yaml"),
+            "include: [bones.yaml]\nproducts: []\ndish:\n  ingredients: []\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("bones.yaml"), "products: []\n").unwrap();
+        let fixed = names(recipe.files().await.unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(missing, ["soup.yaml", "stock.yaml"]);
+        assert_eq!(broken, ["soup.yaml", "stock.yaml"]);
+        assert_eq!(fixed, ["soup.yaml", "stock.yaml", "bones.yaml"]);
+    }
+
+    #[tokio::test]
+    async fn find_overweight_ingredients() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Flour
+    facts:
+      Energy: 360
+  - name: Salt
+    facts:
+      Sodium: 39000
+dish:
+  ingredients:
+    - product: Flour
+      amount: 2000
+    - product: Salt
+      amount: 5
+  weight: 200
+"#,
+        )
+        .unwrap();
+        let strict = ComputeOptions {
+            strict_weights: true,
+            ..ComputeOptions::default()
         };
 
-        let facts = recipe.get_nutrition_facts();
+        assert_eq!(recipe.overweight_ingredients(), [("Flour", 2000.0, 200.0)]);
+        assert!(recipe.get_nutrition_facts().await.is_ok());
+        match recipe.get_nutrition_facts_with(&strict).await {
+            Err(RecipeError::OverweightIngredient { ingredient, .. }) => {
+                assert_eq!(ingredient, "Flour")
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("overweight ingredient should be rejected"),
+        }
+    }
+
+    #[test]
+    fn build_facts() {
+        let mut facts = NutritionFacts::new();
+        assert_eq!(facts.insert(Nutrition::Energy, 100.0), None);
+        assert_eq!(facts.insert(Nutrition::Energy, 120.0), Some(100.0));
+        facts.insert(Nutrition::Fats, 5.0);
 
         assert_eq!(
             facts,
-            NutritionFacts([(Nutrition::Energy, 500.0)].into_iter().collect())
+            NutritionFacts::from_iter([(Nutrition::Fats, 5.0), (Nutrition::Energy, 120.0)])
+        );
+        assert_eq!(facts.get(Nutrition::Energy), Some(120.0));
+    }
+
+    #[tokio::test]
+    async fn calculate_mixed_bases() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Milk
+    facts:
+      Energy: 60
+      Proteins: 3
+  - name: Granola
+    per:
+      amount: 30
+    facts:
+      Energy: 135
+      Proteins: 3
+dish:
+  ingredients:
+    - product: Milk
+      amount: 200
+    - product: Granola
+      amount: 60
+"#,
+        )
+        .unwrap();
+
+        let facts = recipe.get_total_nutrition().await.unwrap();
+
+        assert!(recipe.products.iter().all(|product| product.per.is_none()));
+        assert_eq!(facts.get(Nutrition::Energy), Some(390.0));
+        assert_eq!(facts.get(Nutrition::Proteins), Some(12.0));
+    }
+
+    #[tokio::test]
+    async fn compare_recipes() {
+        let recipe = Recipe::from_yaml_str(
+            "products:\n  - name: Oil\n    facts:\n      Energy: 900\naliases:\n  oo: Oil\n  evoo: Oil\ndish:\n  ingredients:\n    - product: oo\n      amount: 10\n",
+        )
+        .unwrap();
+        let reformatted = Recipe::from_json_str(
+            r#"{"dish": {"ingredients": [{"product": "oo", "amount": 10.0}]},
+                "aliases": {"evoo": "Oil", "oo": "Oil"},
+                "products": [{"name": "Oil", "facts": {"Energy": 900.0}}]}"#,
+        )
+        .unwrap();
+        let scaled = recipe.scaled(2.0);
+
+        let recipes: HashSet<Recipe> = [recipe.clone(), reformatted.clone(), scaled.clone()]
+            .into_iter()
+            .collect();
+
+        assert!(recipe == reformatted);
+        assert!(recipe != scaled);
+        assert_eq!(recipes.len(), 2);
+
+        let original = Recipe::read_from_file(Path::new("tests/fixtures/library/shortbread.yaml"))
+            .await
+            .unwrap();
+        let copy = Recipe::read_from_file(Path::new("tests/fixtures/library/copy/shortbread.yaml"))
+            .await
+            .unwrap();
+        assert!(original == copy);
+
+        let undefined = recipe.scaled(f64::NAN);
+        let recipes: HashSet<Recipe> = [undefined.clone(), undefined.clone()].into_iter().collect();
+        assert!(undefined == undefined.clone());
+        assert_eq!(recipes.len(), 1);
+        assert_eq!(
+            NutritionFacts::from_iter([(Nutrition::Fats, -0.0)]),
+            NutritionFacts::from_iter([(Nutrition::Fats, 0.0)])
+        );
+    }
+
+    #[tokio::test]
+    async fn calculate_scaled_product() {
+        let yaml = |scale: &str| {
+            format!(
+                "products:\n  - name: Syrup\n{}    facts:\n      Energy: 300\n      Sugars: 70\n  - name: Water\n    facts: {{}}\ndish:\n  ingredients:\n    - product: Syrup\n      amount: 100\n    - product: Water\n      amount: 100\n",
+                scale
+            )
+        };
+        let plain = Recipe::from_yaml_str(&yaml("")).unwrap();
+        let diluted = Recipe::from_yaml_str(&yaml("    scale: 0.5\n")).unwrap();
+
+        let plain = plain.get_total_nutrition().await.unwrap();
+        let diluted = diluted.get_total_nutrition().await.unwrap();
+
+        assert_eq!(plain.get(Nutrition::Energy), Some(300.0));
+        assert_eq!(diluted.get(Nutrition::Energy), Some(150.0));
+        assert_eq!(diluted.get(Nutrition::Sugars), Some(35.0));
+        for scale in ["0", "-1", ".inf"] {
+            match Recipe::from_yaml_str(&yaml(&format!("    scale: {}\n", scale))) {
+                Err(RecipeError::InvalidValue { field, .. }) => {
+                    assert_eq!(field, "products[Syrup].scale")
+                }
+                Err(err) => panic!("unexpected error: {}", err),
+                Ok(_) => panic!("scale {} should be rejected", scale),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn calculate_per_ounce() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Jerky
+    basis: per_oz
+    facts:
+      Energy: 80
+      Proteins: 9
+dish:
+  ingredients:
+    - product: Jerky
+      amount: 100
+"#,
+        )
+        .unwrap();
+
+        let facts = recipe.get_nutrition_facts().await.unwrap();
+
+        let per_100g = 100.0 / 28.349523125;
+        assert!((facts.get(Nutrition::Energy).unwrap() - 80.0 * per_100g).abs() < 1e-9);
+        assert!((facts.get(Nutrition::Proteins).unwrap() - 9.0 * per_100g).abs() < 1e-9);
+        assert!(matches!(
+            Recipe::from_yaml_str(
+                "products:\n  - name: Jerky\n    basis: per_oz\n    per:\n      amount: 28\n    facts:\n      Energy: 80\ndish:\n  ingredients: []\n"
+            ),
+            Err(RecipeError::ConflictingBasis { product }) if product == "Jerky"
+        ));
+    }
+
+    #[tokio::test]
+    async fn calculate_portion() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Fudge
+    facts:
+      Energy: 500
+      Fats: 20
+dish:
+  ingredients:
+    - product: Fudge
+      amount: 300
+"#,
+        )
+        .unwrap();
+
+        let facts = recipe.get_nutrition_facts_for_portion(150.0).await.unwrap();
+
+        assert_eq!(facts.get(Nutrition::Energy), Some(750.0));
+        assert_eq!(facts.get(Nutrition::Fats), Some(30.0));
+        assert!(matches!(
+            recipe.get_nutrition_facts_for_portion(0.0).await,
+            Err(RecipeError::InvalidValue { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn fail_unknown_fields_when_strict() {
+        let yaml = r#"
+products:
+  - name: Oats
+    facts:
+      Energy: 370
+dish:
+  ingredients:
+    - product: Oats
+      amount: 100
+  wieght: 90
+"#;
+
+        let lenient = Recipe::from_str_as(yaml, InputFormat::Yaml).unwrap();
+        assert_eq!(
+            lenient
+                .get_nutrition_facts()
+                .await
+                .unwrap()
+                .get(Nutrition::Energy),
+            Some(370.0)
+        );
+        match Recipe::from_str_strict(yaml, InputFormat::Yaml) {
+            Err(RecipeError::UnknownFields { fields }) => assert_eq!(fields, ["dish.wieght"]),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("misspelled field should be rejected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn calculate_blend() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Wheat flour
+    allergens: [Gluten]
+    facts:
+      Energy: 340
+      Proteins: 10
+  - name: Rice flour
+    facts:
+      Energy: 360
+      Proteins: 6
+  - name: Flour mix
+    blend:
+      - product: wheat flour
+        percent: 50
+      - product: Rice flour
+        percent: 50
+dish:
+  ingredients:
+    - product: Flour mix
+      amount: 200
+"#,
+        )
+        .unwrap();
+
+        let facts = recipe.get_total_nutrition().await.unwrap();
+
+        assert_eq!(facts.get(Nutrition::Energy), Some(700.0));
+        assert_eq!(facts.get(Nutrition::Proteins), Some(16.0));
+        assert!(recipe.unused_products().is_empty());
+        assert_eq!(recipe.allergens().await.unwrap(), ["gluten"]);
+    }
+
+    #[tokio::test]
+    async fn fail_blend_cycle() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Mix
+    blend:
+      - product: Rice
+        percent: 50
+      - product: Other mix
+        percent: 50
+  - name: Other mix
+    blend:
+      - product: mix
+        percent: 100
+  - name: Rice
+    facts:
+      Energy: 130
+dish:
+  ingredients:
+    - product: Mix
+      amount: 100
+"#,
+        )
+        .unwrap();
+
+        let err = recipe.get_nutrition_facts().await.unwrap_err();
+
+        match err.root_cause() {
+            RecipeError::BlendCycle { chain } => assert_eq!(chain, &["Mix", "Other mix", "Mix"]),
+            err => panic!("unexpected error: {}", err),
+        }
+        assert_eq!(err.exit_code(), 35);
+    }
+
+    #[test]
+    fn fail_blend_percentage() {
+        let err = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Rice
+    facts:
+      Energy: 130
+  - name: Mix
+    blend:
+      - product: Rice
+        percent: 60
+      - product: Rice
+        percent: 30
+dish:
+  ingredients:
+    - product: Mix
+      amount: 100
+"#,
+        )
+        .err()
+        .unwrap();
+
+        match err {
+            RecipeError::BlendPercentage { product, total } => {
+                assert_eq!(product, "Mix");
+                assert_eq!(total, 90.0);
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn calculate_percent_of_dish_weight() {
+        let yaml = r#"
+products:
+  - name: Flour
+    facts:
+      Carbohydrates: 70
+  - name: Salt
+    facts:
+      Sodium: 38000
+dish:
+  weight: 1000
+  ingredients:
+    - product: Flour
+      amount: 950
+    - product: Salt
+      amount: 5
+      unit: "%"
+"#;
+        let recipe = Recipe::from_yaml_str(yaml).unwrap();
+
+        let computed = recipe.compute().await.unwrap();
+        let breakdown = recipe.breakdown().await.unwrap();
+
+        assert_eq!(computed.raw_weight, 1000.0);
+        assert_eq!(breakdown[1].1.get(Nutrition::Sodium), Some(19000.0));
+        assert_eq!(
+            recipe.scaled(2.0).compute().await.unwrap().raw_weight,
+            2000.0
+        );
+
+        let recipe = Recipe::from_yaml_str(&yaml.replace("  weight: 1000\n", "")).unwrap();
+        match recipe.compute().await.err().unwrap() {
+            RecipeError::MissingDishWeight { ingredient } => assert_eq!(ingredient, "Salt"),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn fail_missing_weight_when_strict() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Rice
+    facts:
+      Energy: 130
+dish:
+  ingredients:
+    - product: Rice
+      amount: 200
+"#,
+        )
+        .unwrap();
+        let strict = ComputeOptions {
+            strict_weights: true,
+            ..ComputeOptions::default()
+        };
+
+        let lenient = recipe.get_nutrition_facts().await.unwrap();
+        let err = recipe
+            .get_nutrition_facts_with(&strict)
+            .await
+            .err()
+            .unwrap();
+        let weighed = recipe.with_weight(400.0).unwrap();
+
+        assert_eq!(lenient.get(Nutrition::Energy), Some(130.0));
+        assert!(matches!(err, RecipeError::MissingWeight), "{}", err);
+        assert_eq!(
+            weighed
+                .get_nutrition_facts_with(&strict)
+                .await
+                .unwrap()
+                .get(Nutrition::Energy),
+            Some(65.0)
+        );
+    }
+
+    #[tokio::test]
+    async fn round_for_label() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Cheese
+    facts:
+      Energy: 402.6
+      Fats: 33.14
+      Sodium: 621.4
+      Calcium: 721.37
+dish:
+  ingredients:
+    - product: Cheese
+      amount: 30
+  servings: 1
+"#,
+        )
+        .unwrap();
+        let label = ComputeOptions {
+            rounding: RoundingPolicy::Label,
+            ..ComputeOptions::default()
+        };
+
+        let raw = recipe.get_nutrition_facts().await.unwrap();
+        let rounded = recipe.get_nutrition_facts_with(&label).await.unwrap();
+        let serving = recipe
+            .get_nutrition_facts_per_serving_with(&label)
+            .await
+            .unwrap();
+
+        assert_eq!(raw.get(Nutrition::Fats), Some(33.14));
+        assert_eq!(rounded.get(Nutrition::Energy), Some(403.0));
+        assert_eq!(rounded.get(Nutrition::Fats), Some(33.1));
+        assert_eq!(rounded.get(Nutrition::Sodium), Some(621.0));
+        assert_eq!(
+            rounded.get(Nutrition::from_name("Calcium")),
+            raw.get(Nutrition::from_name("Calcium"))
+        );
+        assert_eq!(serving.get(Nutrition::Fats), Some(9.9));
+        assert_eq!(serving.get(Nutrition::Energy), Some(121.0));
+    }
+
+    #[tokio::test]
+    async fn calculate_whole_recipe_basis() {
+        let recipe = Recipe::from_yaml_str(
+            r#"
+products:
+  - name: Batch
+    recipe:
+      path: tests/fixtures/whole/dough.yaml
+      basis: whole
+  - name: Dough
+    recipe: tests/fixtures/whole/dough.yaml
+dishes:
+  - name: Batches
+    ingredients:
+      - product: Batch
+        amount: 2
+  - name: Weighed
+    ingredients:
+      - product: Dough
+        amount: 250
+      - product: Batch
+        amount: 250
+        unit: g
+"#,
+        )
+        .unwrap();
+
+        let components: HashMap<&str, Recipe> = recipe.components().into_iter().collect();
+        let batches = components["Batches"].compute().await.unwrap();
+        let weighed = components["Weighed"].compute().await.unwrap();
+
+        assert_eq!(batches.raw_weight, 1000.0);
+        assert_eq!(batches.facts.get(Nutrition::Energy), Some(210.0));
+        assert_eq!(
+            components["Batches"]
+                .get_total_nutrition()
+                .await
+                .unwrap()
+                .get(Nutrition::Energy),
+            Some(2100.0)
+        );
+        assert_eq!(weighed.raw_weight, 500.0);
+        assert_eq!(weighed.facts.get(Nutrition::Energy), Some(210.0));
+        match components["Batches"].with_weight(500.0).err().unwrap() {
+            RecipeError::UnknownRecipeWeight { product } => assert_eq!(product, "Batch"),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    /// Recipe of one product per `(energy, fats, grams)` triple, each used
+    /// for the given grams.
+    fn recipe_of(ingredients: &[(f64, f64, f64)]) -> Recipe {
+        ingredients
+            .iter()
+            .enumerate()
+            .fold(
+                super::RecipeBuilder::new(),
+                |builder, (i, &(energy, fats, grams))| {
+                    let name = format!("Product {}", i);
+                    builder
+                        .product(Product::new(
+                            name.clone(),
+                            [(Nutrition::Energy, energy), (Nutrition::Fats, fats)],
+                        ))
+                        .ingredient(Ingredient::new(name, grams))
+                },
+            )
+            .build()
+            .unwrap()
+    }
+
+    fn assert_facts_close(actual: &NutritionFacts, expected: &NutritionFacts) {
+        assert_eq!(
+            actual.nutrients().collect::<Vec<_>>(),
+            expected.nutrients().collect::<Vec<_>>()
+        );
+        for (nutrient, amount) in expected.iter() {
+            let actual = actual.get(nutrient.clone()).unwrap();
+            assert!(
+                (actual - amount).abs() <= 1e-9 * amount.abs().max(1.0),
+                "{}: {} != {}",
+                nutrient,
+                actual,
+                amount
+            );
+        }
+    }
+
+    fn ingredients() -> impl Strategy<Value = Vec<(f64, f64, f64)>> {
+        proptest::collection::vec((0.0..900.0, 0.0..100.0, 0.1..1000.0), 1..8)
+    }
+
+    fn facts() -> impl Strategy<Value = NutritionFacts> {
+        proptest::collection::btree_map(
+            proptest::sample::select(Nutrition::KNOWN.to_vec()),
+            0.0..1000.0,
+            0..Nutrition::KNOWN.len(),
         )
+        .prop_map(NutritionFacts)
+    }
+
+    proptest! {
+        #[test]
+        fn scaling_keeps_facts_per_100g(ingredients in ingredients(), factor in 0.01..100.0) {
+            let recipe = recipe_of(&ingredients);
+
+            let facts = block_on(recipe.get_nutrition_facts()).unwrap();
+            let scaled = block_on(recipe.scaled(factor).get_nutrition_facts()).unwrap();
+
+            assert_facts_close(&scaled, &facts);
+        }
+
+        #[test]
+        fn facts_per_100g_stay_within_the_products(ingredients in ingredients()) {
+            let facts = block_on(recipe_of(&ingredients).get_nutrition_facts()).unwrap();
+
+            let energy = facts.get(Nutrition::Energy).unwrap();
+            let energies = ingredients.iter().map(|&(energy, _, _)| energy);
+            prop_assert!(energy >= energies.clone().fold(f64::INFINITY, f64::min) - 1e-9);
+            prop_assert!(energy <= energies.fold(0.0, f64::max) + 1e-9);
+        }
+
+        #[test]
+        fn adding_facts_commutes(a in facts(), b in facts()) {
+            assert_facts_close(&(a.clone() + b.clone()), &(b + a));
+        }
+
+        #[test]
+        fn ingredient_order_keeps_facts(mut ingredients in ingredients()) {
+            let facts = block_on(recipe_of(&ingredients).get_nutrition_facts()).unwrap();
+            ingredients.reverse();
+            let reversed = block_on(recipe_of(&ingredients).get_nutrition_facts()).unwrap();
+
+            assert_facts_close(&reversed, &facts);
+        }
     }
 }
@@ -0,0 +1,224 @@
+//! JSON Schema of recipe files, for editors to complete and check them.
+
+use schemars::{json_schema, Schema, SchemaGenerator};
+use serde_json::Value;
+
+use crate::{
+    range::Amount,
+    recipe::{Nutrition, Recipe},
+    unit::Unit,
+};
+
+/// JSON Schema of recipe files, generated from the types they are read
+/// into.
+pub fn recipe_schema() -> serde_json::Value {
+    schemars::schema_for!(Recipe).to_value()
+}
+
+/// Paths of the fields of a parsed recipe that recipe files don't have, like
+/// `dish.wieght` or `products[Oats].fat`. Items of lists are named by their
+/// `name` or `product` when they have one and by their index otherwise.
+pub(crate) fn unknown_fields(recipe: &Value) -> Vec<String> {
+    let schema = recipe_schema();
+    let mut unknown = Vec::new();
+    find_unknown(
+        recipe,
+        &[&schema],
+        &schema["$defs"],
+        String::new(),
+        &mut unknown,
+    );
+    unknown
+}
+
+/// Schemas `schema` stands for, with references resolved and alternatives
+/// it's one of expanded.
+fn alternatives<'a>(schema: &'a Value, defs: &'a Value) -> Vec<&'a Value> {
+    if let Some(name) = schema["$ref"]
+        .as_str()
+        .and_then(|r| r.strip_prefix("#/$defs/"))
+    {
+        return alternatives(&defs[name], defs);
+    }
+    let mut all = vec![schema];
+    for key in ["oneOf", "anyOf", "allOf"] {
+        for alternative in schema[key].as_array().into_iter().flatten() {
+            all.extend(alternatives(alternative, defs));
+        }
+    }
+    all
+}
+
+/// Pushes paths of the fields of `value` that none of `schemas` has.
+fn find_unknown(
+    value: &Value,
+    schemas: &[&Value],
+    defs: &Value,
+    path: String,
+    unknown: &mut Vec<String>,
+) {
+    let schemas: Vec<&Value> = schemas
+        .iter()
+        .flat_map(|schema| alternatives(schema, defs))
+        .collect();
+    match value {
+        Value::Object(fields) => {
+            let objects: Vec<&Value> = schemas
+                .into_iter()
+                .filter(|schema| {
+                    schema.get("properties").is_some()
+                        || schema.get("additionalProperties").is_some()
+                })
+                .collect();
+            if objects.is_empty() {
+                return;
+            }
+            for (key, field) in fields {
+                let field_path = match path.as_str() {
+                    "" => key.clone(),
+                    path => format!("{}.{}", path, key),
+                };
+                let mut field_schemas: Vec<&Value> = objects
+                    .iter()
+                    .filter_map(|schema| schema["properties"].get(key))
+                    .collect();
+                if field_schemas.is_empty() {
+                    field_schemas = objects
+                        .iter()
+                        .filter_map(|schema| schema.get("additionalProperties"))
+                        .filter(|schema| **schema != Value::Bool(false))
+                        .collect();
+                }
+                if field_schemas.is_empty() {
+                    unknown.push(field_path);
+                } else {
+                    find_unknown(field, &field_schemas, defs, field_path, unknown);
+                }
+            }
+        }
+        Value::Array(items) => {
+            let item_schemas: Vec<&Value> = schemas
+                .iter()
+                .filter_map(|schema| schema.get("items"))
+                .collect();
+            for (i, item) in items.iter().enumerate() {
+                let name = item
+                    .get("name")
+                    .or_else(|| item.get("product"))
+                    .and_then(Value::as_str)
+                    .map_or_else(|| i.to_string(), String::from);
+                let item_path = format!("{}[{}]", path, name);
+                find_unknown(item, &item_schemas, defs, item_path, unknown);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Facts of a product, listing the known nutrients while allowing custom
+/// ones.
+pub(crate) fn facts(generator: &mut SchemaGenerator) -> Schema {
+    let amount = generator.subschema_for::<Amount>();
+    let properties: serde_json::Map<String, serde_json::Value> = Nutrition::KNOWN
+        .iter()
+        .map(|nutrient| {
+            let mut schema = amount.clone();
+            let description = match nutrient.unit() {
+                "" => format!("{} per the basis of the product", nutrient),
+                unit => format!("{} in {} per the basis of the product", nutrient, unit),
+            };
+            schema.insert("description".into(), description.into());
+            (nutrient.name().to_string(), schema.to_value())
+        })
+        .collect();
+    json_schema!({
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": amount,
+    })
+}
+
+/// Symbol of a unit, one of [`Unit::SYMBOLS`].
+pub(crate) fn unit(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": ["string", "null"],
+        "enum": Unit::SYMBOLS.iter().map(|symbol| Some(*symbol)).chain([None]).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeSet, fs};
+
+    use serde_json::Value;
+
+    use super::{recipe_schema, unknown_fields};
+    use crate::Unit;
+
+    /// Properties of `schema` and of the alternatives it's one of.
+    fn keys(schema: &Value) -> BTreeSet<&str> {
+        let own = schema["properties"].as_object().into_iter().flatten();
+        let alternatives = schema["oneOf"].as_array().into_iter().flatten();
+        own.map(|(key, _)| key.as_str())
+            .chain(alternatives.flat_map(keys))
+            .collect()
+    }
+
+    #[test]
+    fn schema_covers_recipes() {
+        let schema = recipe_schema();
+        let recipe_keys = keys(&schema);
+        let product_keys = keys(&schema["$defs"]["Product"]);
+        for entry in fs::read_dir("recipes").unwrap() {
+            let path = entry.unwrap().path();
+            let recipe: Value = serde_yaml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            for key in recipe.as_object().unwrap().keys() {
+                assert!(recipe_keys.contains(key.as_str()), "{:?}: {}", path, key);
+            }
+            for product in recipe["products"].as_array().unwrap() {
+                for key in product.as_object().unwrap().keys() {
+                    assert!(product_keys.contains(key.as_str()), "{:?}: {}", path, key);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn find_unknown_fields() {
+        let recipe: Value = serde_yaml::from_str(
+            r#"
+author: Ann
+products:
+  - name: Oats
+    facts:
+      Energy: 370
+      Omega-3: 0.1
+    densty: 0.4
+dish:
+  ingredients:
+    - product: Oats
+      amount: 100
+      unti: g
+  wieght: 90
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            unknown_fields(&recipe),
+            [
+                "author",
+                "dish.ingredients[Oats].unti",
+                "dish.wieght",
+                "products[Oats].densty"
+            ]
+        );
+    }
+
+    #[test]
+    fn unit_symbols() {
+        for symbol in Unit::SYMBOLS {
+            assert!(Unit::from_symbol(symbol).is_some(), "{}", symbol);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! HTTP endpoint computing facts of posted recipes, for frontends.
+//!
+//! `POST /facts` with a recipe in the body answers with its facts per 100g as
+//! JSON. The body is read as JSON when its content type says so and as YAML
+//! otherwise. Bodies are to be sent with a `Content-Length`, chunked ones
+//! being refused. Recipes including libraries or made of sub-recipes are
+//! refused too, so that clients can't have the server's files read.
+
+use std::{io, time::Duration};
+
+use log::{debug, warn};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+
+use crate::recipe::{ComputeOptions, InputFormat, ParseOptions, Recipe};
+
+/// Largest request head and body accepted.
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// How long a client may take to send its whole request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Answers requests accepted on `listener` until accepting fails, computing
+/// facts with `options`.
+pub async fn serve(listener: TcpListener, options: ComputeOptions) -> io::Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let options = options.clone();
+        tokio::spawn(async move {
+            debug!("connection from {}", peer);
+            if let Err(err) = handle(stream, &options).await {
+                warn!("Failed to answer {}: {}", peer, err);
+            }
+        });
+    }
+}
+
+/// Status line and JSON body of a response.
+struct Response {
+    status: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn error(status: &'static str, message: impl ToString) -> Self {
+        Self {
+            status,
+            body: serde_json::json!({ "error": message.to_string() }).to_string(),
+        }
+    }
+}
+
+async fn handle(mut stream: TcpStream, options: &ComputeOptions) -> io::Result<()> {
+    let response = match timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(request) => match request? {
+            Ok(request) => respond(request, options).await,
+            Err(response) => response,
+        },
+        Err(_) => Response::error("408 Request Timeout", "Request took too long to send"),
+    };
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(response.body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+struct Request {
+    method: String,
+    path: String,
+    format: InputFormat,
+    body: Vec<u8>,
+}
+
+/// Reads one request, or the response rejecting it when it's malformed, too
+/// large or without a length.
+async fn read_request(stream: &mut TcpStream) -> io::Result<Result<Request, Response>> {
+    let mut buffer = Vec::new();
+    let head_end = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            return Ok(Err(Response::error(
+                "431 Request Header Fields Too Large",
+                "Request head is too large",
+            )));
+        }
+        let mut chunk = [0; 4096];
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(Err(Response::error(
+                "400 Bad Request",
+                "Incomplete request",
+            )));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+    let mut body = buffer.split_off(head_end + 4);
+    let Ok(head) = std::str::from_utf8(&buffer[..head_end]) else {
+        return Ok(Err(Response::error(
+            "400 Bad Request",
+            "Request head isn't UTF-8",
+        )));
+    };
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let (Some(method), Some(path)) = (request_line.next(), request_line.next()) else {
+        return Ok(Err(Response::error(
+            "400 Bad Request",
+            "Malformed request line",
+        )));
+    };
+    let mut length = None;
+    let mut chunked = false;
+    let mut format = InputFormat::Yaml;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            match value.parse() {
+                Ok(value) => length = Some(value),
+                Err(_) => {
+                    return Ok(Err(Response::error(
+                        "400 Bad Request",
+                        "Invalid Content-Length",
+                    )))
+                }
+            }
+        } else if name.eq_ignore_ascii_case("content-type") && value.contains("json") {
+            format = InputFormat::Json;
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = true;
+        }
+    }
+    let length = match length {
+        Some(length) if !chunked => length,
+        None if !chunked && method != "POST" => 0,
+        _ => {
+            return Ok(Err(Response::error(
+                "411 Length Required",
+                "Recipe is to be sent with a Content-Length",
+            )))
+        }
+    };
+    if length > MAX_BODY_BYTES {
+        return Ok(Err(Response::error(
+            "413 Payload Too Large",
+            "Recipe is too large",
+        )));
+    }
+    body.truncate(length);
+    let mut rest = vec![0; length - body.len()];
+    stream.read_exact(&mut rest).await?;
+    body.extend(rest);
+    Ok(Ok(Request {
+        method: method.into(),
+        path: path.into(),
+        format,
+        body,
+    }))
+}
+
+async fn respond(request: Request, options: &ComputeOptions) -> Response {
+    if request.path != "/facts" {
+        return Response::error(
+            "404 Not Found",
+            format!("No such endpoint: {}", request.path),
+        );
+    }
+    if request.method != "POST" {
+        return Response::error("405 Method Not Allowed", "Recipes are to be POSTed");
+    }
+    let Ok(body) = String::from_utf8(request.body) else {
+        return Response::error("400 Bad Request", "Recipe isn't UTF-8");
+    };
+    let parse = ParseOptions {
+        no_files: true,
+        ..options.parse
+    };
+    let facts = match Recipe::from_str_with(&body, request.format, parse) {
+        Ok(recipe) => recipe.get_nutrition_facts_with(options).await,
+        Err(err) => Err(err),
+    };
+    match facts.map(|facts| serde_json::to_string(&facts)) {
+        Ok(Ok(body)) => Response {
+            status: "200 OK",
+            body,
+        },
+        Ok(Err(err)) => Response::error("500 Internal Server Error", err),
+        Err(err) => Response::error("422 Unprocessable Entity", err),
+    }
+}
@@ -0,0 +1,78 @@
+//! Time spent on the steps of computing facts of large recipe trees.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Time spent reading, parsing and aggregating recipe files, summed over
+/// every file and every computation the timings are shared by. Concurrent
+/// steps are summed too, so totals may exceed the wall-clock time.
+#[derive(Debug, Default)]
+pub struct Timings {
+    files: AtomicUsize,
+    io_nanos: AtomicU64,
+    parsing_nanos: AtomicU64,
+    aggregation_nanos: AtomicU64,
+}
+
+impl Timings {
+    /// Recipe files read.
+    pub fn files(&self) -> usize {
+        self.files.load(Ordering::Relaxed)
+    }
+
+    /// Time spent reading recipe and library files.
+    pub fn io(&self) -> Duration {
+        load(&self.io_nanos)
+    }
+
+    /// Time spent parsing and validating recipes and libraries.
+    pub fn parsing(&self) -> Duration {
+        load(&self.parsing_nanos)
+    }
+
+    /// Time spent summing what ingredients contribute.
+    pub fn aggregation(&self) -> Duration {
+        load(&self.aggregation_nanos)
+    }
+
+    pub(crate) fn count_file(&self) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_io(&self, since: Instant) {
+        add(&self.io_nanos, since);
+    }
+
+    pub(crate) fn add_parsing(&self, since: Instant) {
+        add(&self.parsing_nanos, since);
+    }
+
+    pub(crate) fn add_aggregation(&self, since: Instant) {
+        add(&self.aggregation_nanos, since);
+    }
+}
+
+fn load(nanos: &AtomicU64) -> Duration {
+    Duration::from_nanos(nanos.load(Ordering::Relaxed))
+}
+
+fn add(nanos: &AtomicU64, since: Instant) {
+    let elapsed = since.elapsed().as_nanos().try_into().unwrap_or(u64::MAX);
+    nanos.fetch_add(elapsed, Ordering::Relaxed);
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Files read: {}, I/O: {:.3?}, parsing: {:.3?}, aggregation: {:.3?}",
+            self.files(),
+            self.io(),
+            self.parsing(),
+            self.aggregation()
+        )
+    }
+}
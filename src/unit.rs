@@ -0,0 +1,93 @@
+/// Unit an ingredient amount is given in.
+///
+/// Volume units are converted to grams through the density of the product,
+/// cups are US customary cups of 240 ml. Pieces and servings are converted
+/// through the weight of a piece or a serving of the product, percentages
+/// through the weight of the dish.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    Gram,
+    Kilogram,
+    Milligram,
+    Ounce,
+    Pound,
+    Milliliter,
+    Liter,
+    Cup,
+    Tablespoon,
+    Teaspoon,
+    Piece,
+    Serving,
+    Percent,
+}
+
+impl Unit {
+    /// Symbols [`Unit::from_symbol`] knows.
+    pub const SYMBOLS: [&'static str; 14] = [
+        "g", "kg", "mg", "oz", "lb", "ml", "l", "cup", "tbsp", "tsp", "pcs", "piece", "serving",
+        "%",
+    ];
+
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "g" => Some(Unit::Gram),
+            "kg" => Some(Unit::Kilogram),
+            "mg" => Some(Unit::Milligram),
+            "oz" => Some(Unit::Ounce),
+            "lb" => Some(Unit::Pound),
+            "ml" => Some(Unit::Milliliter),
+            "l" => Some(Unit::Liter),
+            "cup" => Some(Unit::Cup),
+            "tbsp" => Some(Unit::Tablespoon),
+            "tsp" => Some(Unit::Teaspoon),
+            "pcs" | "piece" => Some(Unit::Piece),
+            "serving" => Some(Unit::Serving),
+            "%" => Some(Unit::Percent),
+            _ => None,
+        }
+    }
+
+    /// Converts an amount given in this unit to grams, `None` when this is a
+    /// volume unit and no density in g/ml is given, pieces or servings and no
+    /// weight of one is given, or a percentage, which needs the weight of the
+    /// dish.
+    pub fn to_grams(
+        &self,
+        amount: f64,
+        density: Option<f64>,
+        grams_per_piece: Option<f64>,
+        serving_weight: Option<f64>,
+    ) -> Option<f64> {
+        match self {
+            Unit::Piece => return grams_per_piece.map(|grams| amount * grams),
+            Unit::Serving => return serving_weight.map(|grams| amount * grams),
+            Unit::Percent => return None,
+            _ => {}
+        }
+        match self.milliliters() {
+            Some(milliliters) => density.map(|density| amount * milliliters * density),
+            None => Some(amount * self.grams()),
+        }
+    }
+
+    fn grams(&self) -> f64 {
+        match self {
+            Unit::Kilogram => 1000.0,
+            Unit::Milligram => 0.001,
+            Unit::Ounce => 28.349523125,
+            Unit::Pound => 453.59237,
+            _ => 1.0,
+        }
+    }
+
+    fn milliliters(&self) -> Option<f64> {
+        match self {
+            Unit::Milliliter => Some(1.0),
+            Unit::Liter => Some(1000.0),
+            Unit::Cup => Some(240.0),
+            Unit::Tablespoon => Some(15.0),
+            Unit::Teaspoon => Some(5.0),
+            _ => None,
+        }
+    }
+}
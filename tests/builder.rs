@@ -0,0 +1,45 @@
+use nutritions::{Ingredient, Nutrition, Product, RecipeBuilder, RecipeError};
+
+#[tokio::test]
+async fn build_recipe() {
+    let recipe = RecipeBuilder::new()
+        .product(Product::new(
+            "Milk",
+            [(Nutrition::Energy, 60.0), (Nutrition::Fats, 3.2)],
+        ))
+        .product(Product::new("Egg", [(Nutrition::Energy, 155.0)]).grams_per_piece(50.0))
+        .product(Product::from_recipe(
+            "Sauce",
+            "tests/fixtures/nested-inner.yaml",
+        ))
+        .ingredient(Ingredient::new("Milk", 200.0))
+        .ingredient(Ingredient::new("Egg", 2.0).unit("pcs"))
+        .ingredient(Ingredient::new("Sauce", 100.0))
+        .cooked_weight(250.0)
+        .build()
+        .unwrap();
+
+    let facts = recipe.get_nutrition_facts().await.unwrap();
+
+    assert_eq!(
+        facts.to_string(),
+        "Energy:  117.20 kcal\nProteins:  0.36 g\nFats:  2.64 g\nCarbohydrates:  1.56 g\n"
+    );
+}
+
+#[test]
+fn fail_build_invalid_recipe() {
+    let err = RecipeBuilder::new()
+        .product(Product::new("Milk", [(Nutrition::Energy, 60.0)]))
+        .ingredient(Ingredient::new("Milk", -1.0))
+        .build()
+        .err()
+        .unwrap();
+
+    match err {
+        RecipeError::InvalidValue { field, .. } => {
+            assert_eq!(field, "dish.ingredients[Milk].amount")
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+}
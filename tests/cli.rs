@@ -0,0 +1,75 @@
+use std::process::{Command, Output};
+
+fn nutritions(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_nutritions"))
+        .args(args)
+        .arg("--no-default-db")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn parse_directory_recipes_strictly() {
+    let lenient = nutritions(&["--dir", "tests/fixtures/strict"]);
+    let strict = nutritions(&["--dir", "tests/fixtures/strict", "--strict-parse"]);
+
+    assert!(String::from_utf8_lossy(&lenient.stdout).contains("soup.yaml"));
+    assert!(!String::from_utf8_lossy(&strict.stdout).contains("soup.yaml"));
+    assert!(String::from_utf8_lossy(&strict.stderr).contains("products[Oil].wieght"));
+}
+
+#[test]
+fn search_path_from_env() {
+    let recipe = "tests/fixtures/search-recipe.yaml";
+    let found = Command::new(env!("CARGO_BIN_EXE_nutritions"))
+        .args(["-r", recipe, "--no-default-db"])
+        .env("NUTRITIONS_PATH", "tests/fixtures/search")
+        .output()
+        .unwrap();
+    let missing = Command::new(env!("CARGO_BIN_EXE_nutritions"))
+        .args(["-r", recipe, "--no-default-db"])
+        .env_remove("NUTRITIONS_PATH")
+        .output()
+        .unwrap();
+
+    assert!(found.status.success());
+    assert!(String::from_utf8_lossy(&found.stdout).contains("374.27"));
+    assert_eq!(missing.status.code(), Some(12));
+}
+
+#[test]
+fn supply_products_from_default_database() {
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_nutritions"))
+            .args(args)
+            .env("NUTRITIONS_CONFIG_DIR", "tests/fixtures/default-db/config")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?} failed", args);
+        let log = String::from_utf8_lossy(&output.stderr);
+        assert!(!log.contains("WARN"), "{}", log);
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let recipe = run(&[
+        "-r",
+        "tests/fixtures/default-db/recipes/dressing.yaml",
+        "-v",
+    ]);
+    let dir = run(&["--dir", "tests/fixtures/default-db/recipes"]);
+    let plan = run(&["--meal-plan", "tests/fixtures/default-db/day.yaml"]);
+    let without = nutritions(&["--dir", "tests/fixtures/default-db/recipes"]);
+
+    assert!(recipe.contains("Energy:  90.00 kcal"), "{}", recipe);
+    assert!(
+        dir.contains("dressing.yaml") && dir.contains("90.00"),
+        "{}",
+        dir
+    );
+    assert!(
+        plan.contains("Facts: Total\nEnergy:  90.00 kcal"),
+        "{}",
+        plan
+    );
+    assert!(!String::from_utf8_lossy(&without.stdout).contains("dressing.yaml"));
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "serve")]
+
+use nutritions::{serve::serve, ComputeOptions};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+async fn post(addr: std::net::SocketAddr, content_type: &str, body: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!(
+        "POST /facts HTTP/1.1\r\nHost: localhost\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn serve_facts() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve(listener, ComputeOptions::default()));
+
+    let response = post(
+        addr,
+        "application/yaml",
+        "products:\n  - name: Rice\n    facts:\n      Energy: 130\n      Carbohydrates: 28\ndish:\n  ingredients:\n    - product: Rice\n      amount: 200\n",
+    )
+    .await;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+    assert_eq!(body, r#"{"Energy":130.0,"Carbohydrates":28.0}"#);
+
+    let response = post(addr, "application/json", r#"{"products": []}"#).await;
+    assert!(response.starts_with("HTTP/1.1 422 "), "{}", response);
+}
+
+#[tokio::test]
+async fn refuse_file_references() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve(listener, ComputeOptions::default()));
+
+    for recipe in [
+        "include: [/etc/passwd]\nproducts: []\ndish:\n  ingredients: []\n",
+        "products:\n  - name: Secret\n    recipe: /etc/passwd\ndish:\n  ingredients: []\n",
+    ] {
+        let response = post(addr, "application/yaml", recipe).await;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+
+        assert!(head.starts_with("HTTP/1.1 422 "), "{}", head);
+        assert!(body.contains("may not refer to files"), "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn refuse_chunked_recipes() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve(listener, ComputeOptions::default()));
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(
+            b"POST /facts HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+              10\r\n{\"products\": []}\r\n0\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 411 "), "{}", response);
+}